@@ -1,14 +1,37 @@
-use std::{collections::HashSet, error::Error, fs::read_dir, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    error::Error,
+    fs::read_dir,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use csv::ReaderBuilder;
-use egui::{ProgressBar, RichText, Visuals};
-use log::{error, info};
+use csv::{ReaderBuilder, StringRecord};
+use egui::{Color32, RichText, Visuals};
+use log::{error, info, warn};
 
 use crate::{
-    category::CategoriesHolder,
-    config::{Config, Input},
+    attributes::AttributeForm,
+    captions::Captions,
+    category::{CategoriesHolder, CategoryEditor},
+    config::{CategoryEdit, Config, ConfigWatcher, Input, Mode, Sample},
     data_loader::{DataLoader, Image},
-    progression::Progression,
+    grid::GridView,
+    inference::Classifier,
+    metadata::Metadata,
+    notes::NoteEditor,
+    pairwise::PairwiseView,
+    pre_labels::PreLabels,
+    progression::{DirectoryProgression, Progression},
+    rating::Ratings,
+    regions::Regions,
+    review::Review,
+    sampling::load_sample,
+    shortcuts::shortcuts_enabled,
+    statistics::Statistics,
+    summary::{Summary, SummaryAction},
+    uncertainty::UncertaintyQueue,
 };
 
 enum InputKind {
@@ -16,17 +39,72 @@ enum InputKind {
     Csv,
 }
 
+impl InputKind {
+    /// Path shown above the image, directory inputs only show the sub
+    /// directory and the file name.
+    fn display(&self, path: &Path) -> String {
+        let path = match self {
+            InputKind::Dir => path
+                .iter()
+                .rev()
+                .take(2)
+                .collect::<PathBuf>()
+                .iter()
+                .rev()
+                .collect::<PathBuf>(),
+            InputKind::Csv => path.to_path_buf(),
+        };
+
+        path.to_string_lossy().into_owned()
+    }
+}
+
+enum Screen {
+    Labeling,
+    Summary(Summary),
+    Review(Box<Review>),
+}
+
 pub struct ImagePicker {
+    screen: Screen,
     current_image: Option<Image>,
 
     dataloader: DataLoader,
 
     category: CategoriesHolder,
+    category_editor: CategoryEditor,
     output_dir: PathBuf,
 
+    input: Input,
     input_kind: InputKind,
+    sample: Option<Sample>,
+    pre_labels: Option<PreLabels>,
+    queue: Option<UncertaintyQueue>,
+    classifier: Option<Arc<Classifier>>,
+    /// Only in grid mode
+    grid: Option<GridView>,
+    /// Only in pairwise mode
+    pairwise: Option<PairwiseView>,
+    /// Only in regions mode
+    regions: Option<Regions>,
+    /// Only in rating mode
+    ratings: Option<Ratings>,
+    /// Only when attributes are configured, in single and grid modes
+    attributes: Option<AttributeForm>,
+    /// Only in single and grid modes
+    notes: Option<NoteEditor>,
+    /// Only when a caption column is configured, in single mode
+    captions: Option<Captions>,
+    /// Columns of the CSV input written in the category CSVs
+    metadata: Metadata,
+
+    config_path: PathBuf,
+    config_watcher: ConfigWatcher,
+    config_error: Option<String>,
+    stop_when_quotas_met: bool,
 
     progression: Progression,
+    statistics: Statistics,
 }
 
 impl ImagePicker {
@@ -36,124 +114,735 @@ impl ImagePicker {
     ) -> Result<Self, Box<dyn Error>> {
         cc.egui_ctx.set_visuals(Visuals::dark());
 
+        let reserved_keys = config.reserved_keys();
         let Config {
             input,
             output_dir,
             categories,
+            attributes,
+            notes,
+            stop_when_quotas_met,
+            sample,
+            predictions,
+            queue,
+            model,
+            mode,
+            sources,
         } = config;
 
+        info!(
+            "Reading input {} and exporting to {}",
+            input.path().display(),
+            output_dir.display()
+        );
         std::fs::create_dir_all(&output_dir)?;
 
+        let pre_labels = predictions
+            .map(|x| PreLabels::load(x, &input.root(), &output_dir))
+            .transpose()?;
+
         let mut category_tree = CategoriesHolder::from(categories);
         category_tree.load_paths(&output_dir)?;
 
         let input_kind = match &input {
             Input::Dir { root: _ } => InputKind::Dir,
-            Input::Csv { ds: _, root: _ } => InputKind::Csv,
+            Input::Csv { .. } => InputKind::Csv,
+        };
+        let regions = match mode {
+            Mode::Regions => Some(Regions::load(&output_dir)?),
+            _ => None,
+        };
+        let ratings = match mode {
+            Mode::Rating {
+                min,
+                max,
+                half_steps,
+            } => Some(Ratings::load(min, max, half_steps, &output_dir)?),
+            _ => None,
+        };
+        let attributes = match mode {
+            _ if attributes.is_empty() => None,
+            Mode::Single | Mode::Grid { .. } => Some(AttributeForm::load(attributes, &output_dir)?),
+            _ => {
+                warn!("Attributes are only recorded in single and grid modes");
+                None
+            }
+        };
+        let notes = match mode {
+            Mode::Single | Mode::Grid { .. } => Some(NoteEditor::load(notes, &output_dir)?),
+            _ => None,
+        };
+        let mut paths_to_exclude = category_tree.get_paths();
+        if let Some(regions) = &regions {
+            paths_to_exclude.extend(regions.get_paths());
+        }
+        if let Some(ratings) = &ratings {
+            paths_to_exclude.extend(ratings.get_paths());
+        }
+
+        let ImageList {
+            mut paths,
+            nb_categorized,
+            directories,
+            captions,
+            metadata,
+        } = make_image_list(
+            input.clone(),
+            paths_to_exclude,
+            sample.as_ref(),
+            &output_dir,
+        )?;
+        let captions = match mode {
+            _ if !matches!(
+                input,
+                Input::Csv {
+                    caption_column: Some(_),
+                    ..
+                }
+            ) =>
+            {
+                None
+            }
+            Mode::Single => Some(Captions::load(captions, &output_dir)?),
+            _ => {
+                warn!("Captions are only shown in single mode");
+                None
+            }
+        };
+
+        let classifier = model.map(Classifier::load).transpose()?.map(Arc::new);
+        if let Some(classifier) = &classifier {
+            let names = category_tree.names();
+
+            classifier
+                .categories()
+                .filter(|x| !names.contains(x))
+                .for_each(|x| warn!("The model suggests \"{}\" which isn't a category", x));
+        }
+
+        if let Some(pre_labels) = &pre_labels {
+            pre_labels.check_paths(&paths);
+        }
+
+        let queue = queue.map(UncertaintyQueue::load).transpose()?;
+        if let Some(queue) = &queue {
+            queue.sort(&mut paths);
+        }
+
+        // Pairs are compared instead of images labeled
+        let pairwise = match &mode {
+            Mode::Pairwise { pairs } => Some(PairwiseView::new(pairs, &paths, &output_dir)?),
+            _ => None,
+        };
+        let progression = match &pairwise {
+            Some(pairwise) => {
+                paths = pairwise.paths();
+                Progression::new(pairwise.get_nb_pairs(), pairwise.get_nb_compared(), vec![])
+            }
+            None => Progression::new(paths.len(), nb_categorized, directories),
         };
-        let paths = make_image_list(input, category_tree.get_paths())?;
-        let progression = Progression::new(paths.len());
 
         Ok(Self {
+            screen: Screen::Labeling,
             current_image: None,
 
-            dataloader: DataLoader::new(25, paths),
+            dataloader: DataLoader::new(25, paths, classifier.clone()),
 
             category: category_tree,
+            category_editor: CategoryEditor::new(reserved_keys),
             output_dir,
 
+            input,
             input_kind,
+            sample,
+            pre_labels,
+            queue,
+            classifier,
+            grid: match mode {
+                Mode::Grid { size } => Some(GridView::new(size)),
+                _ => None,
+            },
+            pairwise,
+            regions,
+            ratings,
+            attributes,
+            notes,
+            captions,
+            metadata,
+
+            config_path: sources[0].clone(),
+            config_watcher: ConfigWatcher::new(cc.egui_ctx.clone(), sources),
+            config_error: None,
+            stop_when_quotas_met,
 
             progression,
+            statistics: Statistics::new(),
         })
     }
 
     fn handle_current(&mut self) {
-        if self.category.is_there_a_selected_category() {
+        // The category stays selected until the attributes are fixed
+        if !self.category.has_selected_category() || !self.validate_attributes() {
+            return;
+        }
+        if let Some(category) = self.category.take_selected_category() {
             if let Some(image) = self.current_image.take() {
-                self.category.add_path_to_selected_category(image.source);
+                self.assign(&category, vec![image.source]);
+
+                if self.is_done_by_quotas() {
+                    self.screen = Screen::Summary(self.make_summary());
+                    return;
+                }
+
                 self.read_next_image();
                 std::thread::sleep(std::time::Duration::from_millis(100));
             }
         }
     }
 
+    /// Invalid attributes keep the images until they're fixed.
+    fn validate_attributes(&mut self) -> bool {
+        match &mut self.attributes {
+            Some(attributes) => attributes.validate(),
+            None => true,
+        }
+    }
+
+    fn assign(&mut self, category: &str, paths: Vec<PathBuf>) {
+        for path in paths {
+            if let Some(pre_labels) = &mut self.pre_labels {
+                pre_labels.record(&path, category);
+            }
+            if let Some(attributes) = &mut self.attributes {
+                attributes.record(&path, category);
+            }
+            if let Some(notes) = &mut self.notes {
+                notes.record(&path, category);
+            }
+            if let Some(captions) = &mut self.captions {
+                captions.record(&path, category);
+            }
+            self.category.add_path(category, path);
+            self.statistics.record_label();
+
+            if let Some(queue) = &mut self.queue {
+                if queue.record_label() {
+                    self.dataloader.reorder(|paths| queue.sort(paths));
+                }
+            }
+        }
+
+        if let Some(attributes) = &mut self.attributes {
+            attributes.reset();
+        }
+        if let Some(notes) = &mut self.notes {
+            notes.reset();
+        }
+    }
+
+    fn update_pairwise(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut egui::Ui,
+        pairwise: &mut PairwiseView,
+    ) {
+        let left = match pairwise.next(&mut self.dataloader) {
+            Some(left) => left,
+            None => {
+                self.screen = Screen::Summary(self.make_summary());
+                return;
+            }
+        };
+        ui.vertical_centered(|ui| {
+            ui.heading("Which image is better?");
+            self.progression.update(ui);
+        });
+
+        if pairwise.update(ctx, ui) {
+            self.progression.step(&left);
+            self.statistics.record_label();
+        }
+    }
+
+    fn update_regions(&mut self, ctx: &egui::Context, ui: &mut egui::Ui, regions: &mut Regions) {
+        if self.current_image.is_none() {
+            self.read_next_image();
+        }
+        let image = match &self.current_image {
+            Some(image) => image,
+            None => {
+                self.screen = Screen::Summary(self.make_summary());
+                return;
+            }
+        };
+
+        ui.vertical_centered(|ui| {
+            ui.heading(self.input_kind.display(&image.source));
+            self.progression.update(ui);
+        });
+
+        let shapes = self.category.shapes();
+        if let Some(category) = self.category.take_selected_category() {
+            regions.label(category, &shapes);
+        }
+        if regions.update(ctx, ui, image, &shapes) {
+            regions.finish(image);
+            self.statistics.record_label();
+            self.read_next_image();
+        }
+    }
+
+    fn update_rating(&mut self, ctx: &egui::Context, ui: &mut egui::Ui, ratings: &mut Ratings) {
+        if self.current_image.is_none() {
+            self.read_next_image();
+        }
+        let image = match &self.current_image {
+            Some(image) => image,
+            None => {
+                self.screen = Screen::Summary(self.make_summary());
+                return;
+            }
+        };
+
+        ui.vertical_centered(|ui| {
+            ui.heading(self.input_kind.display(&image.source));
+            self.progression.update(ui);
+        });
+
+        // Categories aren't assigned when rating
+        self.category.take_selected_category();
+        if ratings.update(ctx, ui, image) {
+            self.statistics.record_label();
+            self.read_next_image();
+        }
+    }
+
+    fn update_grid(&mut self, ctx: &egui::Context, ui: &mut egui::Ui, grid: &mut GridView) {
+        // Quotas may already be met when resuming a session
+        while !grid.is_full() && !self.is_done_by_quotas() {
+            match self.dataloader.read_current() {
+                Some(image) => {
+                    self.progression.step(&image.source);
+                    grid.push(image);
+                }
+                None => break,
+            }
+        }
+        if grid.is_empty() {
+            self.screen = Screen::Summary(self.make_summary());
+            return;
+        }
+
+        ui.vertical_centered(|ui| {
+            ui.heading(format!(
+                "{} selected out of {}",
+                grid.get_nb_selected(),
+                grid.len()
+            ));
+            self.progression.update(ui);
+        });
+        grid.update(ctx, ui);
+
+        if !self.category.has_selected_category() || !self.validate_attributes() {
+            return;
+        }
+        // The form applies to every selected image
+        if let Some(category) = self.category.take_selected_category() {
+            // The cursor image is assigned when none is selected
+            if !self
+                .category
+                .has_room(&category, grid.get_nb_selected().max(1))
+            {
+                return;
+            }
+            let paths = grid.take_selected().into_iter().map(|x| x.source).collect();
+            self.assign(&category, paths);
+
+            if self.is_done_by_quotas() {
+                self.screen = Screen::Summary(self.make_summary());
+            }
+        }
+    }
+
+    fn is_done_by_quotas(&self) -> bool {
+        self.stop_when_quotas_met && self.category.are_quotas_met()
+    }
+
+    fn make_summary(&self) -> Summary {
+        if self.pairwise.is_some() {
+            Summary::new("Every pair has been compared")
+        } else if self.ratings.is_some() {
+            Summary::new("Every image has been rated")
+        } else if self.is_done_by_quotas() {
+            Summary::new("Every quota is met")
+        } else {
+            Summary::new("Every image has been categorized")
+        }
+    }
+
+    fn apply_config(&mut self, config: Config) {
+        if config.input != self.input
+            || config.output_dir != self.output_dir
+            || config.sample != self.sample
+            || config.predictions.as_ref() != self.pre_labels.as_ref().map(PreLabels::config)
+            || config.queue.as_ref() != self.queue.as_ref().map(UncertaintyQueue::config)
+            || config.model.as_ref() != self.classifier.as_deref().map(Classifier::config)
+            || matches!(config.mode, Mode::Grid { .. }) != self.grid.is_some()
+            || matches!(config.mode, Mode::Pairwise { .. }) != self.pairwise.is_some()
+            || matches!(config.mode, Mode::Regions) != self.regions.is_some()
+            || matches!(config.mode, Mode::Rating { .. }) != self.ratings.is_some()
+            || config.attributes.as_slice()
+                != self
+                    .attributes
+                    .as_ref()
+                    .map_or(&[][..], AttributeForm::schema)
+            || self
+                .notes
+                .as_ref()
+                .is_some_and(|x| x.config() != &config.notes)
+        {
+            warn!("Only category changes are applied before restarting");
+        }
+
+        // Categories removed from the config keep their images on disk
+        if let Err(e) = self.export() {
+            error!("{:?}", e);
+        }
+
+        self.category_editor
+            .set_reserved_keys(config.reserved_keys());
+        if let Err(e) = self.category.reload(config.categories, &self.output_dir) {
+            error!("Couldn't reload categories: {}", e);
+            self.config_error = Some(format!("Couldn't read the categories' CSVs.\n{}", e));
+            return;
+        }
+        self.stop_when_quotas_met = config.stop_when_quotas_met;
+        self.config_error = None;
+        info!("Config reloaded");
+    }
+
+    fn reload_config(&mut self) {
+        match self.config_watcher.poll() {
+            Some(Ok(config)) => self.apply_config(config),
+            Some(Err(e)) => {
+                error!("Couldn't reload config: {}", e);
+                self.config_error =
+                    Some(format!("Invalid config, keeping the previous one.\n{}", e));
+            }
+            None => {}
+        }
+    }
+
+    /// Writes a category edit to the config and applies it right away.
+    fn edit_categories(&mut self, edit: CategoryEdit) {
+        let result = self
+            .config_watcher
+            .write_unwatched(|| edit.write(&self.config_path));
+        if let Err(e) = result {
+            error!("Couldn't edit the config: {}", e);
+            self.config_error = Some(format!("Couldn't edit the config.\n{}", e));
+            return;
+        }
+
+        // Paths are migrated by name, so the category is renamed beforehand
+        if let CategoryEdit::Rename { name, new_name } = &edit {
+            if let Err(e) = self.category.rename(name, new_name, &self.output_dir) {
+                error!("Couldn't rename the CSV of {}: {}", name, e);
+            }
+
+            // The other exports record the category by name as well
+            if let Some(attributes) = &mut self.attributes {
+                attributes.rename_category(name, new_name);
+            }
+            if let Some(notes) = &mut self.notes {
+                notes.rename_category(name, new_name);
+            }
+            if let Some(captions) = &mut self.captions {
+                captions.rename_category(name, new_name);
+            }
+            if let Some(pre_labels) = &mut self.pre_labels {
+                pre_labels.rename_category(name, new_name);
+            }
+            if let Some(regions) = &mut self.regions {
+                regions.rename_category(name, new_name);
+            }
+        }
+
+        match Config::try_from(self.config_path.as_path()) {
+            Ok(config) => self.apply_config(config),
+            Err(e) => {
+                error!("Couldn't reload config: {}", e);
+                self.config_error = Some(format!("Invalid config after edit.\n{}", e));
+            }
+        }
+    }
+
+    fn handle_summary_action(&mut self, action: SummaryAction, frame: &mut eframe::Frame) {
+        match action {
+            SummaryAction::Export => {
+                let status = match self.export() {
+                    Ok(()) => format!("Exported to {}", self.output_dir.display()),
+                    Err(e) => {
+                        error!("{:?}", e);
+                        format!("Couldn't export: {}", e)
+                    }
+                };
+
+                if let Screen::Summary(summary) = &mut self.screen {
+                    summary.set_status(status);
+                }
+            }
+            SummaryAction::Review(category) => {
+                let paths = self.category.get_category_paths(&category);
+                let notes = self
+                    .notes
+                    .as_ref()
+                    .map(NoteEditor::texts)
+                    .unwrap_or_default();
+                self.screen = Screen::Review(Box::new(Review::new(category, paths, notes)));
+            }
+            SummaryAction::Quit => frame.quit(),
+        }
+    }
+
+    fn export(&self) -> Result<(), Box<dyn Error>> {
+        self.category
+            .export_paths(&self.output_dir, &self.metadata)?;
+
+        if let Some(pre_labels) = &self.pre_labels {
+            pre_labels.export(&self.output_dir)?;
+        }
+        if let Some(pairwise) = &self.pairwise {
+            pairwise.export(&self.output_dir)?;
+        }
+        if let Some(regions) = &self.regions {
+            regions.export(
+                &self.output_dir,
+                &self.input.root(),
+                &self.category.shapes(),
+            )?;
+        }
+        if let Some(ratings) = &self.ratings {
+            ratings.export(&self.output_dir)?;
+        }
+        if let Some(attributes) = &self.attributes {
+            attributes.export(&self.output_dir)?;
+        }
+        if let Some(notes) = &self.notes {
+            notes.export(&self.output_dir)?;
+        }
+        if let Some(captions) = &self.captions {
+            captions.export(&self.output_dir)?;
+        }
+
+        Ok(())
+    }
+
     fn read_next_image(&mut self) {
         self.current_image = self.dataloader.read_current();
-        if self.current_image.is_some() {
-            self.progression.step();
+        if let Some(image) = &self.current_image {
+            self.progression.step(&image.source);
         }
     }
 }
 
 impl eframe::App for ImagePicker {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.reload_config();
+
+        if let Some(config_error) = &self.config_error {
+            let mut dismissed = false;
+
+            egui::TopBottomPanel::top("Config error").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.colored_label(Color32::RED, config_error);
+                    dismissed = ui.button("Dismiss").clicked();
+                });
+            });
+
+            if dismissed {
+                self.config_error = None;
+            }
+        }
         egui::SidePanel::new(egui::panel::Side::Left, "Categories tree")
             .resizable(true)
             .show(ctx, |ui| {
-                ui.label("Categories");
-                ui.vertical(|ui| {
-                    self.category.update(ctx, ui);
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.label("Categories");
+                    ui.vertical(|ui| {
+                        self.category.update(ctx, ui);
+                    });
+
+                    ui.separator();
+                    self.statistics
+                        .update(ui, &self.progression, &self.category);
+
+                    ui.separator();
+                    if let Some(edit) = self.category_editor.update(ui, &self.category) {
+                        self.edit_categories(edit);
+                    }
                 });
             });
-        egui::CentralPanel::default().show(ctx, |ui| {
-            if let Some(image) = self.current_image.as_ref() {
-                ui.vertical_centered(|ui| {
-                    let image_path = image.source.clone();
-                    let path = match self.input_kind {
-                        InputKind::Dir => image_path
-                            .iter()
-                            .rev()
-                            .take(2)
-                            .collect::<PathBuf>()
-                            .iter()
-                            .rev()
-                            .collect::<PathBuf>(),
-                        InputKind::Csv => image_path,
-                    };
-
-                    ui.heading(path.to_str().unwrap_or_default());
-                    ui.label(RichText::new(format!(
-                        "{} left",
-                        self.progression.get_nb_remaining_step()
-                    )));
-
-                    ui.add(ProgressBar::new(self.progression.compute_progress()));
-                });
-
-                ui.with_layout(
-                    egui::Layout::top_down_justified(egui::Align::Center),
-                    |ui| {
-                        let buffer = &image.buffer;
-                        let factors = ui.available_size() / buffer.size_vec2();
+        if let (Screen::Labeling, Some(attributes)) = (&self.screen, &mut self.attributes) {
+            egui::SidePanel::right("Attributes")
+                .resizable(true)
+                .show(ctx, |ui| attributes.update(ctx, ui));
+        }
+        if let (Screen::Labeling, Some(notes)) = (&self.screen, &mut self.notes) {
+            egui::TopBottomPanel::bottom("Notes").show(ctx, |ui| notes.update(ctx, ui));
+        }
+        if let (Screen::Labeling, Some(captions), Some(image)) =
+            (&self.screen, &mut self.captions, &self.current_image)
+        {
+            egui::TopBottomPanel::bottom("Caption")
+                .show(ctx, |ui| captions.update(ui, &image.source));
+        }
+        egui::CentralPanel::default().show(ctx, |ui| match &mut self.screen {
+            Screen::Summary(summary) => {
+                if let Some(action) = summary.update(
+                    ui,
+                    &self.category,
+                    &self.statistics,
+                    self.dataloader.get_nb_unreadable(),
+                ) {
+                    self.handle_summary_action(action, frame);
+                }
+            }
+            Screen::Review(review) => {
+                if review.update(ctx, ui) {
+                    self.screen = Screen::Summary(self.make_summary());
+                }
+            }
+            Screen::Labeling if self.pairwise.is_some() => {
+                if let Some(mut pairwise) = self.pairwise.take() {
+                    self.update_pairwise(ctx, ui, &mut pairwise);
+                    self.pairwise = Some(pairwise);
+                }
+            }
+            Screen::Labeling if self.regions.is_some() => {
+                if let Some(mut regions) = self.regions.take() {
+                    self.update_regions(ctx, ui, &mut regions);
+                    self.regions = Some(regions);
+                }
+            }
+            Screen::Labeling if self.ratings.is_some() => {
+                if let Some(mut ratings) = self.ratings.take() {
+                    self.update_rating(ctx, ui, &mut ratings);
+                    self.ratings = Some(ratings);
+                }
+            }
+            Screen::Labeling if self.grid.is_some() => {
+                if let Some(mut grid) = self.grid.take() {
+                    self.update_grid(ctx, ui, &mut grid);
+                    self.grid = Some(grid);
+                }
+            }
+            Screen::Labeling => {
+                if let Some(image) = self.current_image.as_ref() {
+                    ui.vertical_centered(|ui| {
+                        ui.heading(self.input_kind.display(&image.source));
+                        self.progression.update(ui);
+
+                        if let Some(pre_labels) = &self.pre_labels {
+                            if let Some(prediction) = pre_labels.get(&image.source) {
+                                ui.label(
+                                    RichText::new(format!(
+                                        "Predicted: {} ({:.2}), press {:?} to accept",
+                                        prediction.category,
+                                        prediction.score,
+                                        pre_labels.accept_key()
+                                    ))
+                                    .strong(),
+                                );
+
+                                // Shortcuts with Ctrl are left to notes
+                                if shortcuts_enabled(ctx)
+                                    && !ctx.input().modifiers.command
+                                    && ctx.input().key_pressed(pre_labels.accept_key())
+                                {
+                                    self.category.select(&prediction.category);
+                                }
+                            }
+                        }
 
-                        buffer.show_scaled(ui, factors.min_elem());
-                    },
-                );
-                self.handle_current();
-            } else {
-                self.read_next_image();
+                        if let Some(classifier) = &self.classifier {
+                            ui.horizontal_wrapped(|ui| {
+                                ui.label("Suggestions:");
+
+                                image
+                                    .suggestions
+                                    .iter()
+                                    .zip(classifier.accept_keys())
+                                    .for_each(|(suggestion, key)| {
+                                        ui.label(format!(
+                                            "[{:?}] {} ({:.2})",
+                                            key, suggestion.category, suggestion.score
+                                        ));
+
+                                        // Ctrl with a digit toggles a tag
+                                        if shortcuts_enabled(ctx)
+                                            && !ctx.input().modifiers.command
+                                            && ctx.input().key_pressed(*key)
+                                        {
+                                            self.category.select(&suggestion.category);
+                                        }
+                                    });
+                            });
+                        }
+                    });
+
+                    ui.with_layout(
+                        egui::Layout::top_down_justified(egui::Align::Center),
+                        |ui| {
+                            let buffer = &image.buffer;
+                            let factors = ui.available_size() / buffer.size_vec2();
+
+                            buffer.show_scaled(ui, factors.min_elem());
+                        },
+                    );
+                    self.handle_current();
+                } else {
+                    // Quotas may already be met when resuming a session
+                    if !self.is_done_by_quotas() {
+                        self.read_next_image();
+                    }
 
-                if self.current_image.is_none() {
-                    frame.quit();
+                    if self.current_image.is_none() {
+                        self.screen = Screen::Summary(self.make_summary());
+                    }
                 }
             }
         });
     }
 
     fn on_exit(&mut self, _gl: &eframe::glow::Context) {
-        self.category
-            .export_paths(&self.output_dir)
-            .map_err(|e| error!("{:?}", e));
+        if let Err(e) = self.export() {
+            error!("{:?}", e);
+        }
     }
 }
 
+struct ImageList {
+    paths: Vec<PathBuf>,
+    nb_categorized: usize,
+    directories: Vec<DirectoryProgression>,
+    /// Caption of each image, for CSV inputs with a caption column
+    captions: HashMap<PathBuf, String>,
+    metadata: Metadata,
+}
+
 fn make_image_list(
     input: Input,
     paths_to_exclude: HashSet<PathBuf>,
-) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    sample: Option<&Sample>,
+    output_dir: &Path,
+) -> Result<ImageList, Box<dyn Error>> {
     let mut paths = vec![];
-    let mut nb_removed_paths = 0;
+    let mut categorized = vec![];
+    let mut directories = vec![];
+    let mut captions = HashMap::new();
+    let mut metadata = Metadata::default();
+    let input_path = input.path();
 
     match input {
         Input::Dir { root } => {
@@ -164,59 +853,137 @@ fn make_image_list(
                 let path = entry.path();
 
                 if path.is_dir() {
-                    for entry in read_dir(path)? {
+                    for entry in read_dir(&path)? {
                         let entry = entry?;
                         let path = entry.path();
 
-                        if paths_to_exclude.get(&path).is_some() {
-                            nb_removed_paths += 1;
+                        if paths_to_exclude.contains(&path) {
+                            categorized.push(path);
                         } else if path.is_file() {
-                            paths.push(path)
+                            paths.push(path);
                         }
                     }
+
+                    // Counted once the images are sampled
+                    directories.push(DirectoryProgression {
+                        path,
+                        done: 0,
+                        total: 0,
+                    });
                 }
             }
+            directories.sort_by(|a, b| a.path.cmp(&b.path));
         }
-        Input::Csv { ds, root } => {
-            let csv_path = if let Some(mut root) = root {
-                root.push(ds);
-                root
-            } else {
-                ds
-            };
+        Input::Csv {
+            has_headers,
+            path_column,
+            caption_column,
+            delimiter,
+            ..
+        } => {
+            let csv_path = input_path;
 
             info!("Loading images from CSV file: {}", csv_path.display());
 
+            // Rows may have other columns than the path
             let mut rdr = ReaderBuilder::new()
-                .has_headers(false)
+                .has_headers(has_headers)
+                .delimiter(delimiter.map_or(b',', |x| x as u8))
+                .flexible(true)
                 .from_path(csv_path)?;
+            let headers = if has_headers {
+                Some(rdr.headers()?.clone())
+            } else {
+                None
+            };
+            let path_index = path_column.index(headers.as_ref())?;
+            let caption_index = caption_column
+                .map(|x| x.index(headers.as_ref()))
+                .transpose()?;
+
+            let others = |row: &StringRecord| {
+                row.iter()
+                    .enumerate()
+                    .filter(|x| x.0 != path_index)
+                    .map(|x| x.1.to_string())
+                    .collect::<Vec<_>>()
+            };
+            metadata = Metadata::new(headers.as_ref().map(others));
+
+            for row in rdr.records() {
+                let row = row?;
+                let path = match row.get(path_index) {
+                    Some(path) => PathBuf::from(path),
+                    None => {
+                        warn!("A row has no column {}. Skipping", path_index);
+                        continue;
+                    }
+                };
 
-            paths.extend(
-                rdr.deserialize()
-                    .into_iter()
-                    .collect::<Result<Vec<PathBuf>, _>>()?
-                    .into_iter()
-                    .filter_map(|x| match x.exists() {
-                        false => {
-                            info!("{}: doesn't exist. Skipping", x.display());
-                            None
-                        }
-                        true if paths_to_exclude.get(&x).is_some() => {
-                            nb_removed_paths += 1;
-                            None
-                        }
-                        true => Some(x),
-                    })
-                    .collect::<Vec<PathBuf>>(),
-            );
+                if !path.exists() {
+                    info!("{}: doesn't exist. Skipping", path.display());
+                    continue;
+                }
+                // Images already categorized keep their columns in the export
+                metadata.insert(path.clone(), others(&row));
+
+                if paths_to_exclude.contains(&path) {
+                    categorized.push(path);
+                } else {
+                    if let Some(caption) = caption_index.and_then(|i| row.get(i)) {
+                        captions.insert(path.clone(), caption.to_string());
+                    }
+                    paths.push(path);
+                }
+            }
         }
     };
 
     info!(
         "Found {} images removed {} images already categorized.",
-        paths.len() + nb_removed_paths,
-        nb_removed_paths
+        paths.len() + categorized.len(),
+        categorized.len()
     );
 
-    Ok(paths)
+    // The sample is drawn once, resumed sessions keep labeling it
+    if let Some(sample) = sample {
+        let sampled = load_sample(&paths, sample, output_dir)?;
+        let remaining = paths.into_iter().collect::<HashSet<_>>();
+        let in_sample = sampled.iter().collect::<HashSet<_>>();
+
+        categorized.retain(|x| in_sample.contains(x));
+        paths = sampled
+            .iter()
+            .filter(|x| remaining.contains(*x))
+            .cloned()
+            .collect();
+    }
+
+    // Number of images categorized and in total per directory
+    let mut counts: HashMap<&Path, (usize, usize)> = HashMap::new();
+    for (path, is_categorized) in categorized
+        .iter()
+        .map(|x| (x, true))
+        .chain(paths.iter().map(|x| (x, false)))
+    {
+        if let Some(parent) = path.parent() {
+            let count = counts.entry(parent).or_default();
+            count.0 += usize::from(is_categorized);
+            count.1 += 1;
+        }
+    }
+    for directory in &mut directories {
+        (directory.done, directory.total) = counts
+            .get(directory.path.as_path())
+            .copied()
+            .unwrap_or_default();
+    }
+
+    Ok(ImageList {
+        paths,
+        nb_categorized: categorized.len(),
+        directories,
+        captions,
+        metadata,
+    })
 }
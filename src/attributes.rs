@@ -0,0 +1,271 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use csv::{ReaderBuilder, WriterBuilder};
+use egui::{Color32, RichText, TextEdit};
+use log::info;
+
+use crate::{
+    config::{Attribute, AttributeKind},
+    shortcuts::shortcuts_enabled,
+};
+
+/// Value of an attribute being filled.
+enum Field {
+    Bool(bool),
+    Enum(Option<usize>),
+    Int(String),
+}
+
+impl Field {
+    fn new(kind: &AttributeKind) -> Self {
+        match kind {
+            AttributeKind::Bool => Self::Bool(false),
+            AttributeKind::Enum { .. } => Self::Enum(None),
+            AttributeKind::Int { .. } => Self::Int(String::new()),
+        }
+    }
+
+    /// Value exported for the field, or why it's invalid.
+    fn value(&self, kind: &AttributeKind) -> Result<String, String> {
+        match (self, kind) {
+            (Self::Bool(value), _) => Ok(value.to_string()),
+            (Self::Enum(Some(i)), AttributeKind::Enum { values }) => Ok(values[*i].clone()),
+            (Self::Enum(_), _) => Err("no value chosen".to_string()),
+            (Self::Int(text), AttributeKind::Int { min, max }) => {
+                let value = text
+                    .trim()
+                    .parse::<i64>()
+                    .map_err(|_| format!("\"{}\" isn't an integer", text))?;
+
+                if min.is_some_and(|min| value < min) || max.is_some_and(|max| value > max) {
+                    Err(format!("{} is out of range", value))
+                } else {
+                    Ok(value.to_string())
+                }
+            }
+            (Self::Int(_), _) => Err("not an integer attribute".to_string()),
+        }
+    }
+}
+
+struct Record {
+    category: String,
+    values: HashMap<String, String>,
+}
+
+/// Form of the attributes of the current image, and the attributes recorded
+/// so far.
+pub struct AttributeForm {
+    schema: Vec<Attribute>,
+    fields: Vec<Field>,
+    records: BTreeMap<PathBuf, Record>,
+    /// Columns of a previous export which aren't in the schema anymore
+    extra_columns: Vec<String>,
+    error: Option<String>,
+}
+
+impl AttributeForm {
+    pub const ATTRIBUTES_FILE: &'static str = "attributes.csv";
+
+    /// Loads the attributes recorded in previous sessions.
+    pub fn load(schema: Vec<Attribute>, output_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut records = BTreeMap::new();
+        let mut extra_columns = vec![];
+
+        let attributes_path = output_dir.join(Self::ATTRIBUTES_FILE);
+        if attributes_path.exists() {
+            let mut rdr = ReaderBuilder::new().from_path(&attributes_path)?;
+            let headers = rdr.headers()?.clone();
+            extra_columns = headers
+                .iter()
+                .skip(2)
+                .filter(|x| !schema.iter().any(|attribute| &attribute.name == x))
+                .map(String::from)
+                .collect();
+
+            for row in rdr.records() {
+                let row = row?;
+                let values = headers
+                    .iter()
+                    .zip(row.iter())
+                    .skip(2)
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect();
+
+                records.insert(
+                    PathBuf::from(&row[0]),
+                    Record {
+                        category: row.get(1).unwrap_or_default().to_string(),
+                        values,
+                    },
+                );
+            }
+            info!("Readed attributes of {} images", records.len());
+        }
+
+        Ok(Self {
+            fields: schema.iter().map(|x| Field::new(&x.kind)).collect(),
+            schema,
+            records,
+            extra_columns,
+            error: None,
+        })
+    }
+
+    pub fn schema(&self) -> &[Attribute] {
+        &self.schema
+    }
+
+    /// Checks the form before a category is assigned, showing the first
+    /// invalid attribute.
+    pub fn validate(&mut self) -> bool {
+        self.error = self
+            .schema
+            .iter()
+            .zip(&self.fields)
+            .find_map(|(attribute, field)| {
+                field
+                    .value(&attribute.kind)
+                    .err()
+                    .map(|e| format!("{}: {}", attribute.name, e))
+            });
+
+        self.error.is_none()
+    }
+
+    /// Records the form for an image, it must be valid.
+    pub fn record(&mut self, path: &Path, category: &str) {
+        let values = self
+            .schema
+            .iter()
+            .zip(&self.fields)
+            .filter_map(|(attribute, field)| {
+                let value = field.value(&attribute.kind).ok()?;
+                Some((attribute.name.clone(), value))
+            })
+            .collect();
+
+        self.records.insert(
+            path.to_path_buf(),
+            Record {
+                category: category.to_string(),
+                values,
+            },
+        );
+    }
+
+    /// Renames the category of the images recorded so far.
+    pub fn rename_category(&mut self, name: &str, new_name: &str) {
+        self.records
+            .values_mut()
+            .filter(|x| x.category == name)
+            .for_each(|x| x.category = new_name.to_string());
+    }
+
+    /// Empties the form for the next image.
+    pub fn reset(&mut self) {
+        self.fields = self.schema.iter().map(|x| Field::new(&x.kind)).collect();
+        self.error = None;
+    }
+
+    fn handle_keys(&mut self, ctx: &egui::Context) {
+        let input = ctx.input();
+        if input.modifiers.command {
+            return;
+        }
+
+        for (attribute, field) in self.schema.iter().zip(self.fields.iter_mut()) {
+            if !attribute.key.is_some_and(|key| input.key_pressed(key)) {
+                continue;
+            }
+
+            match (field, &attribute.kind) {
+                (Field::Bool(value), _) => *value = !*value,
+                (Field::Enum(value), AttributeKind::Enum { values }) => {
+                    *value = Some(value.map_or(0, |x| (x + 1) % values.len()));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn update(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        if shortcuts_enabled(ctx) {
+            self.handle_keys(ctx);
+        }
+
+        ui.label("Attributes");
+        for (attribute, field) in self.schema.iter().zip(self.fields.iter_mut()) {
+            let label = match attribute.key {
+                Some(key) => format!("{} [{:?}]", attribute.name, key),
+                None => attribute.name.clone(),
+            };
+
+            match (field, &attribute.kind) {
+                (Field::Bool(value), _) => {
+                    ui.checkbox(value, label);
+                }
+                (Field::Enum(value), AttributeKind::Enum { values }) => {
+                    ui.label(label);
+                    ui.horizontal_wrapped(|ui| {
+                        values.iter().enumerate().for_each(|(i, name)| {
+                            ui.selectable_value(value, Some(i), name);
+                        });
+                    });
+                }
+                (Field::Int(text), AttributeKind::Int { min, max }) => {
+                    let hint = match (min, max) {
+                        (Some(min), Some(max)) => format!("{} to {}", min, max),
+                        (Some(min), None) => format!("{} or more", min),
+                        (None, Some(max)) => format!("{} or less", max),
+                        (None, None) => "integer".to_string(),
+                    };
+
+                    ui.label(label);
+                    ui.add(TextEdit::singleline(text).hint_text(hint));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).color(Color32::RED));
+        }
+    }
+
+    /// Writes a `path,category` row per image followed by its attributes.
+    pub fn export(&self, output_dir: &Path) -> Result<(), Box<dyn Error>> {
+        let columns = self
+            .schema
+            .iter()
+            .map(|x| x.name.as_str())
+            .chain(self.extra_columns.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+
+        let mut wdr = WriterBuilder::new().from_path(output_dir.join(Self::ATTRIBUTES_FILE))?;
+        wdr.write_record(["path", "category"].iter().chain(&columns))?;
+
+        for (path, record) in &self.records {
+            let path = path.to_string_lossy();
+            let values = columns.iter().map(|x| {
+                record
+                    .values
+                    .get(*x)
+                    .map(String::as_str)
+                    .unwrap_or_default()
+            });
+
+            wdr.write_record(
+                [path.as_ref(), record.category.as_str()]
+                    .into_iter()
+                    .chain(values),
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use csv::{ReaderBuilder, WriterBuilder};
+use egui::TextEdit;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct VerifiedCaption {
+    path: PathBuf,
+    category: String,
+    caption: String,
+    /// Whether the caption differs from the input's
+    edited: bool,
+}
+
+/// Captions of the input, checked and corrected along with the category.
+pub struct Captions {
+    captions: HashMap<PathBuf, String>,
+    verified: BTreeMap<PathBuf, VerifiedCaption>,
+    /// Image whose caption is being edited
+    current: Option<PathBuf>,
+    text: String,
+}
+
+impl Captions {
+    pub const CAPTIONS_FILE: &'static str = "captions.csv";
+
+    /// Loads the captions verified in previous sessions.
+    pub fn load(
+        captions: HashMap<PathBuf, String>,
+        output_dir: &Path,
+    ) -> Result<Self, Box<dyn Error>> {
+        let captions_path = output_dir.join(Self::CAPTIONS_FILE);
+        let verified = if captions_path.exists() {
+            ReaderBuilder::new()
+                .from_path(captions_path)?
+                .deserialize()
+                .map(|x| x.map(|x: VerifiedCaption| (x.path.clone(), x)))
+                .collect::<Result<BTreeMap<_, _>, _>>()?
+        } else {
+            BTreeMap::new()
+        };
+        info!("Readed {} verified captions", verified.len());
+
+        Ok(Self {
+            captions,
+            verified,
+            current: None,
+            text: String::new(),
+        })
+    }
+
+    /// Stores the caption of an image as edited so far.
+    pub fn record(&mut self, path: &Path, category: &str) {
+        if self.current.as_deref() != Some(path) {
+            return;
+        }
+        let original = self.captions.get(path).map(String::as_str);
+
+        self.verified.insert(
+            path.to_path_buf(),
+            VerifiedCaption {
+                path: path.to_path_buf(),
+                category: category.to_string(),
+                caption: self.text.trim().to_string(),
+                edited: original.map(str::trim) != Some(self.text.trim()),
+            },
+        );
+        self.current = None;
+    }
+
+    /// Renames the category of the images verified so far.
+    pub fn rename_category(&mut self, name: &str, new_name: &str) {
+        self.verified
+            .values_mut()
+            .filter(|x| x.category == name)
+            .for_each(|x| x.category = new_name.to_string());
+    }
+
+    /// Shows the caption of the image, editable.
+    pub fn update(&mut self, ui: &mut egui::Ui, path: &Path) {
+        if self.current.as_deref() != Some(path) {
+            self.text = self.captions.get(path).cloned().unwrap_or_default();
+            self.current = Some(path.to_path_buf());
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Caption:");
+            ui.add(
+                TextEdit::singleline(&mut self.text)
+                    .hint_text("No caption")
+                    .desired_width(f32::INFINITY),
+            );
+        });
+    }
+
+    pub fn export(&self, output_dir: &Path) -> Result<(), Box<dyn Error>> {
+        let mut wdr = WriterBuilder::new().from_path(output_dir.join(Self::CAPTIONS_FILE))?;
+
+        self.verified
+            .values()
+            .try_for_each(|caption| wdr.serialize(caption))?;
+
+        Ok(())
+    }
+}
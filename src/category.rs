@@ -1,5 +1,7 @@
+mod editor;
 mod holder;
 mod item;
 mod tree;
 
+pub use editor::CategoryEditor;
 pub use holder::CategoriesHolder;
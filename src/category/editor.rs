@@ -0,0 +1,177 @@
+use egui::{CollapsingHeader, Color32, ComboBox, TextEdit};
+
+use super::CategoriesHolder;
+use crate::config::{find_free_key, parse_key, CategoryEdit};
+
+/// Side panel form to add, rename and rebind categories.
+#[derive(Default)]
+pub struct CategoryEditor {
+    name: String,
+    key: String,
+    parent: Option<String>,
+
+    selected: Option<String>,
+    new_name: String,
+    new_key: String,
+
+    /// Keys bound to something else than a category
+    reserved_keys: Vec<egui::Key>,
+
+    error: Option<String>,
+}
+
+impl CategoryEditor {
+    pub fn new(reserved_keys: Vec<egui::Key>) -> Self {
+        Self {
+            reserved_keys,
+            ..Default::default()
+        }
+    }
+
+    /// Updates the reserved keys after the config is reloaded.
+    pub fn set_reserved_keys(&mut self, reserved_keys: Vec<egui::Key>) {
+        self.reserved_keys = reserved_keys;
+    }
+
+    pub fn update(&mut self, ui: &mut egui::Ui, holder: &CategoriesHolder) -> Option<CategoryEdit> {
+        let mut edit = None;
+
+        CollapsingHeader::new("Edit categories")
+            .default_open(false)
+            .show(ui, |ui| {
+                let names = holder.names();
+
+                ui.label("New category");
+                ui.add(TextEdit::singleline(&mut self.name).hint_text("Name"));
+                ui.add(TextEdit::singleline(&mut self.key).hint_text("Key (optional)"));
+                ComboBox::from_id_source("New category parent")
+                    .selected_text(self.parent.as_deref().unwrap_or("No parent"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.parent, None, "No parent");
+                        names.iter().for_each(|name| {
+                            ui.selectable_value(&mut self.parent, Some(name.clone()), name);
+                        });
+                    });
+                if ui.button("Add").clicked() {
+                    edit = self.add(holder);
+                }
+
+                ui.separator();
+
+                ui.label("Existing category");
+                ComboBox::from_id_source("Edited category")
+                    .selected_text(self.selected.as_deref().unwrap_or("Select a category"))
+                    .show_ui(ui, |ui| {
+                        names.iter().for_each(|name| {
+                            ui.selectable_value(&mut self.selected, Some(name.clone()), name);
+                        });
+                    });
+                ui.horizontal(|ui| {
+                    ui.add(TextEdit::singleline(&mut self.new_name).hint_text("New name"));
+                    if ui.button("Rename").clicked() {
+                        edit = self.rename(holder);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.add(TextEdit::singleline(&mut self.new_key).hint_text("New key"));
+                    if ui.button("Rebind").clicked() {
+                        edit = self.rebind(holder);
+                    }
+                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+            });
+
+        edit
+    }
+
+    fn check_name(&mut self, name: &str, holder: &CategoriesHolder) -> bool {
+        if name.is_empty() {
+            self.error = Some("A category needs a name".to_string());
+        } else if holder.names().iter().any(|x| x == name) {
+            self.error = Some(format!("\"{}\" already exists", name));
+        } else if let Some(file) = CategoriesHolder::clashing_export_file(name) {
+            self.error = Some(format!("\"{}\" would be exported over {}", name, file));
+        } else {
+            self.error = None;
+        }
+
+        self.error.is_none()
+    }
+
+    fn check_key(&mut self, key: &str, holder: &CategoriesHolder) -> Option<egui::Key> {
+        self.error = match parse_key(key) {
+            Some(key) if holder.keys().contains(&key) => {
+                Some(format!("{:?} is already bound", key))
+            }
+            Some(key) if self.reserved_keys.contains(&key) => {
+                Some(format!("{:?} is reserved", key))
+            }
+            Some(key) => return Some(key),
+            None => Some(format!("\"{}\" isn't a valid key", key)),
+        };
+
+        None
+    }
+
+    fn free_key(&mut self, name: &str, holder: &CategoriesHolder) -> Option<egui::Key> {
+        let mut used_keys = holder.keys();
+        used_keys.extend(&self.reserved_keys);
+
+        let key = find_free_key(name, &used_keys);
+        if key.is_none() {
+            self.error = Some("No free key left, pick one".to_string());
+        }
+
+        key
+    }
+
+    fn add(&mut self, holder: &CategoriesHolder) -> Option<CategoryEdit> {
+        let name = self.name.trim().to_string();
+        let key = self.key.trim().to_string();
+        if !self.check_name(&name, holder) {
+            return None;
+        }
+        // Written to the config so that the keys assigned to the other
+        // categories don't move on the next load
+        let key = match key.as_str() {
+            "" => self.free_key(&name, holder)?,
+            key => self.check_key(key, holder)?,
+        };
+
+        self.name.clear();
+        self.key.clear();
+
+        Some(CategoryEdit::Add {
+            name,
+            key,
+            parent: self.parent.clone(),
+        })
+    }
+
+    fn rename(&mut self, holder: &CategoriesHolder) -> Option<CategoryEdit> {
+        let name = self.selected.clone()?;
+        let new_name = self.new_name.trim().to_string();
+
+        if !self.check_name(&new_name, holder) {
+            return None;
+        }
+
+        self.selected = Some(new_name.clone());
+        self.new_name.clear();
+
+        Some(CategoryEdit::Rename { name, new_name })
+    }
+
+    fn rebind(&mut self, holder: &CategoriesHolder) -> Option<CategoryEdit> {
+        let name = self.selected.clone()?;
+        let key = self.new_key.trim().to_string();
+        let key = self.check_key(&key, holder)?;
+
+        self.new_key.clear();
+
+        Some(CategoryEdit::Rebind { name, key })
+    }
+}
@@ -1,18 +1,59 @@
-use crate::config::Category;
+use crate::{
+    attributes::AttributeForm,
+    captions::Captions,
+    config::{Category, Shape},
+    metadata::Metadata,
+    notes::NoteEditor,
+    pairwise::PairwiseView,
+    pre_labels::PreLabels,
+    rating::Ratings,
+};
+use csv::WriterBuilder;
+use egui::{Color32, Key};
+use log::warn;
 use std::{
     collections::HashSet,
     error::Error,
     path::{Path, PathBuf},
 };
 
-use super::{item::CategoryTreeItem, tree::CategoryTree};
+use super::{
+    item::CategoryTreeItem,
+    tree::{CategoryCount, CategoryTree},
+};
 
 pub struct CategoriesHolder {
     categories: Vec<CategoryTree>,
     selected_category: Option<String>,
+    warning: Option<String>,
 }
 
 impl CategoriesHolder {
+    /// Header of the exported CSVs, only written when they have one
+    const HEADER_FILE: &'static str = "categories_header.csv";
+    /// Files exported next to the category CSVs
+    const EXPORT_FILES: [&'static str; 8] = [
+        Self::HEADER_FILE,
+        AttributeForm::ATTRIBUTES_FILE,
+        Captions::CAPTIONS_FILE,
+        NoteEditor::NOTES_FILE,
+        PairwiseView::COMPARISONS_FILE,
+        PreLabels::OUTCOMES_FILE,
+        Ratings::RATINGS_FILE,
+        Ratings::HISTOGRAM_FILE,
+    ];
+
+    /// Export file the CSV of a category named `name` would overwrite, if any.
+    pub fn clashing_export_file(name: &str) -> Option<&'static str> {
+        // Named as by `make_category_path`, some file systems ignore case
+        let file_name = Path::new(name).with_extension("csv");
+        let file_name = file_name.to_string_lossy();
+
+        Self::EXPORT_FILES
+            .into_iter()
+            .find(|x| x.eq_ignore_ascii_case(&file_name))
+    }
+
     fn find_item_by_name(&mut self, category_name: &str) -> Option<&mut CategoryTreeItem> {
         let mut item = None;
 
@@ -27,45 +68,228 @@ impl CategoriesHolder {
         item
     }
 
+    /// Category names, parents first.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = vec![];
+
+        self.categories.iter().for_each(|category| {
+            category.for_each_item(&mut |item| names.push(item.name().to_string()))
+        });
+        names
+    }
+
+    /// Category names with their shape, parents first.
+    pub fn shapes(&self) -> Vec<(String, Shape)> {
+        let mut shapes = vec![];
+
+        self.categories.iter().for_each(|category| {
+            category.for_each_item(&mut |item| {
+                shapes.push((item.name().to_string(), item.shape().clone()))
+            })
+        });
+        shapes
+    }
+
+    pub fn keys(&self) -> HashSet<Key> {
+        let mut keys = HashSet::new();
+
+        self.categories.iter().for_each(|category| {
+            category.for_each_item(&mut |item| {
+                keys.insert(item.key());
+            })
+        });
+        keys
+    }
+
+    /// Renames a category along with its exported CSV.
+    pub fn rename(&mut self, name: &str, new_name: &str, output_dir: &Path) -> std::io::Result<()> {
+        if let Some(item) = self.find_item_by_name(name) {
+            let previous_path = item.make_category_path(output_dir);
+            item.set_name(new_name.to_string());
+
+            if previous_path.exists() {
+                std::fs::rename(previous_path, item.make_category_path(output_dir))?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn update(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        self.categories.iter_mut().for_each(|category| {
+            category.update(
+                ctx,
+                ui,
+                &mut self.selected_category,
+                &mut self.warning,
+                false,
+            )
+        });
+
+        if let Some(warning) = &self.warning {
+            ui.colored_label(Color32::YELLOW, warning);
+        }
+    }
+
+    /// Whether there is at least one quota and every quota is met.
+    pub fn are_quotas_met(&self) -> bool {
         self.categories
-            .iter_mut()
-            .map(|category| category.update(ctx, ui, &mut self.selected_category))
-            .collect()
+            .iter()
+            .filter_map(|category| category.are_quotas_met())
+            .reduce(|acc, x| acc && x)
+            .unwrap_or(false)
     }
 
     pub fn get_paths(&self) -> HashSet<PathBuf> {
         self.categories
             .iter()
-            .flat_map(|category| category.get_paths())
+            .flat_map(|category| category.get_paths().iter().cloned())
             .collect()
     }
 
+    /// Number of images of each category, parents first.
+    pub fn counts(&self) -> Vec<CategoryCount> {
+        let mut counts = vec![];
+
+        self.categories
+            .iter()
+            .for_each(|category| category.counts(0, &mut counts));
+        counts
+    }
+
+    /// Sorted paths of a category, including those of its sub categories.
+    pub fn get_category_paths(&self, category_name: &str) -> Vec<PathBuf> {
+        let mut paths = self
+            .categories
+            .iter()
+            .find_map(|category| category.find_by_name(category_name))
+            .map(|category| category.get_paths().iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        paths.sort();
+        paths
+    }
+
     pub fn load_paths(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let has_headers = path.join(Self::HEADER_FILE).exists();
+
         self.categories
             .iter_mut()
-            .try_for_each(|category| category.load_paths(path))?;
+            .try_for_each(|category| category.load_paths(path, has_headers))?;
 
         Ok(())
     }
 
-    pub fn export_paths(&self, output_dir: &Path) -> Result<(), Box<dyn Error>> {
+    pub fn export_paths(
+        &self,
+        output_dir: &Path,
+        metadata: &Metadata,
+    ) -> Result<(), Box<dyn Error>> {
         self.categories
             .iter()
-            .try_for_each(|category| category.export_paths(output_dir))
+            .try_for_each(|category| category.export_paths(output_dir, metadata))?;
+
+        // Tells the next session to skip the first row of the CSVs
+        let header_path = output_dir.join(Self::HEADER_FILE);
+        match metadata.header() {
+            Some(header) => WriterBuilder::new()
+                .from_path(header_path)?
+                .write_record(header)?,
+            None if header_path.exists() => std::fs::remove_file(header_path)?,
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the categories after a config reload. Categories new to the
+    /// holder read the images left in their CSV, the others keep theirs.
+    pub fn reload(
+        &mut self,
+        categories: Vec<Category>,
+        output_dir: &Path,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut holder = Self::from(categories);
+        holder.load_paths(output_dir)?;
+
+        let previous = std::mem::replace(self, holder);
+        self.migrate_paths(previous);
+
+        Ok(())
+    }
+
+    /// Moves the paths of `previous` to the categories with the same name.
+    fn migrate_paths(&mut self, previous: CategoriesHolder) {
+        let mut items = vec![];
+        previous
+            .categories
+            .into_iter()
+            .for_each(|category| category.into_items(&mut items));
+
+        for item in items {
+            if let Some(new_item) = self.find_item_by_name(item.name()) {
+                new_item.set_paths(item.into_paths());
+            } else if !item.get_paths().is_empty() {
+                warn!(
+                    "Category \"{}\" was removed, its {} images are left in its CSV",
+                    item.name(),
+                    item.get_paths().len()
+                );
+            }
+        }
+        self.categories.iter_mut().for_each(CategoryTree::recount);
+    }
+
+    /// Selects a category as if its key was pressed.
+    pub fn select(&mut self, category_name: &str) {
+        match self
+            .categories
+            .iter()
+            .find_map(|category| category.is_blocked(category_name, false))
+        {
+            Some(false) => {
+                self.selected_category = Some(category_name.to_string());
+                self.warning = None;
+            }
+            Some(true) => self.warning = Some(format!("\"{}\" is full", category_name)),
+            None => self.warning = Some(format!("No category named \"{}\"", category_name)),
+        }
+    }
+
+    /// Whether `nb_images` more images fit in the quotas of a category, warns
+    /// with how many still fit otherwise.
+    pub fn has_room(&mut self, category_name: &str, nb_images: usize) -> bool {
+        let room = self
+            .categories
+            .iter()
+            .find_map(|category| category.room(category_name, None))
+            .flatten();
+
+        match room {
+            Some(room) if room < nb_images => {
+                self.warning = Some(format!(
+                    "\"{}\" has room for {} more images, {} are selected",
+                    category_name, room, nb_images
+                ));
+                false
+            }
+            _ => true,
+        }
     }
 
-    pub fn is_there_a_selected_category(&self) -> bool {
+    pub fn has_selected_category(&self) -> bool {
         self.selected_category.is_some()
     }
 
-    pub fn add_path_to_selected_category(&mut self, path: PathBuf) -> bool {
-        if let Some(category) = self.selected_category.take() {
-            self.find_item_by_name(&category).unwrap().add_path(path);
+    pub fn take_selected_category(&mut self) -> Option<String> {
+        self.selected_category.take()
+    }
 
-            true
-        } else {
-            false
+    pub fn add_path(&mut self, category_name: &str, path: PathBuf) {
+        for category in &mut self.categories {
+            if category.add_path(category_name, path.clone()) {
+                break;
+            }
         }
     }
 }
@@ -75,6 +299,70 @@ impl From<Vec<Category>> for CategoriesHolder {
         Self {
             categories: value.into_iter().map(|x| x.into()).collect(),
             selected_category: None,
+            warning: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::Key;
+    use std::path::PathBuf;
+
+    use super::CategoriesHolder;
+    use crate::{
+        config::{Category, Shape},
+        metadata::Metadata,
+    };
+
+    fn category(name: &str, key: Key) -> Category {
+        Category {
+            name: name.to_string(),
+            key: Some(key),
+            sub_categories: None,
+            quota: None,
+            shape: Shape::Box,
+            auto_key: false,
         }
     }
+
+    #[test]
+    fn reload_reads_categories_added_back() {
+        let output_dir = std::env::temp_dir().join("image_picker_holder_reload");
+        let _ = std::fs::remove_dir_all(&output_dir);
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let image = PathBuf::from("images/cat.jpg");
+
+        let mut holder =
+            CategoriesHolder::from(vec![category("cat", Key::C), category("dog", Key::D)]);
+        holder.add_path("cat", image.clone());
+        holder
+            .export_paths(&output_dir, &Metadata::default())
+            .unwrap();
+
+        // Removing the category leaves its CSV on disk
+        holder
+            .reload(vec![category("dog", Key::D)], &output_dir)
+            .unwrap();
+        holder
+            .export_paths(&output_dir, &Metadata::default())
+            .unwrap();
+        assert!(holder.get_category_paths("cat").is_empty());
+
+        holder
+            .reload(
+                vec![category("cat", Key::C), category("dog", Key::D)],
+                &output_dir,
+            )
+            .unwrap();
+        assert_eq!(holder.get_category_paths("cat"), vec![image.clone()]);
+
+        holder
+            .export_paths(&output_dir, &Metadata::default())
+            .unwrap();
+        let csv = std::fs::read_to_string(output_dir.join("cat.csv")).unwrap();
+        assert_eq!(csv.trim(), image.to_string_lossy());
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
 }
@@ -2,17 +2,25 @@ use std::path::{Path, PathBuf};
 
 use egui::Key;
 
+use crate::config::Shape;
+
 pub struct CategoryTreeItem {
     name: String,
     key: Key,
+    auto_key: bool,
+    quota: Option<usize>,
+    shape: Shape,
     paths: Vec<PathBuf>,
 }
 
 impl CategoryTreeItem {
-    pub fn new(name: String, key: Key) -> Self {
+    pub fn new(name: String, key: Key, auto_key: bool, quota: Option<usize>, shape: Shape) -> Self {
         Self {
             name,
             key,
+            auto_key,
+            quota,
+            shape,
             paths: vec![],
         }
     }
@@ -21,6 +29,10 @@ impl CategoryTreeItem {
         &self.name
     }
 
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
     pub fn set_paths(&mut self, paths: Vec<PathBuf>) {
         self.paths = paths;
     }
@@ -29,10 +41,26 @@ impl CategoryTreeItem {
         &self.paths
     }
 
+    pub fn into_paths(self) -> Vec<PathBuf> {
+        self.paths
+    }
+
     pub fn key(&self) -> Key {
         self.key
     }
 
+    pub fn is_key_auto_assigned(&self) -> bool {
+        self.auto_key
+    }
+
+    pub fn quota(&self) -> Option<usize> {
+        self.quota
+    }
+
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
     pub fn add_path(&mut self, path: PathBuf) {
         self.paths.push(path);
     }
@@ -1,7 +1,7 @@
 use super::item::CategoryTreeItem;
-use crate::config::Category;
+use crate::{config::Category, metadata::Metadata, shortcuts::shortcuts_enabled};
 use csv::{ReaderBuilder, WriterBuilder};
-use egui::CollapsingHeader;
+use egui::{collapsing_header::CollapsingState, RichText, SelectableLabel};
 use log::info;
 use std::{
     collections::HashSet,
@@ -9,49 +9,134 @@ use std::{
     path::{Path, PathBuf},
 };
 
+pub struct CategoryCount {
+    pub name: String,
+    pub depth: usize,
+    /// Images which aren't in any sub category
+    pub own: usize,
+    pub total: usize,
+}
+
 pub struct CategoryTree {
     item: CategoryTreeItem,
     leafs: Vec<CategoryTree>,
+    /// Images of the category and its sub categories, kept up to date as
+    /// images are added rather than gathered every frame
+    paths: HashSet<PathBuf>,
+    /// Images which aren't in any sub category
+    nb_own: usize,
 }
 
 impl CategoryTree {
-    pub fn load_paths(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+    /// Reads the exported CSVs, which start with a header when `has_headers`.
+    pub fn load_paths(&mut self, path: &Path, has_headers: bool) -> Result<(), Box<dyn Error>> {
         self.leafs
             .iter_mut()
-            .try_for_each(|leaf| leaf.load_paths(path))?;
+            .try_for_each(|leaf| leaf.load_paths(path, has_headers))?;
 
         let csv_path = self.item.make_category_path(path);
         let paths = if csv_path.exists() {
             let mut rdr = ReaderBuilder::new()
-                .has_headers(false)
+                .has_headers(has_headers)
+                .flexible(true)
                 .from_path(&csv_path)?;
-            let paths: Vec<PathBuf> = rdr.deserialize().into_iter().collect::<Result<_, _>>()?;
+            let mut paths = vec![];
+            for row in rdr.records() {
+                paths.push(PathBuf::from(&row?[0]));
+            }
             info!("Readed {} from {}", paths.len(), csv_path.display());
             paths
         } else {
             vec![]
         };
         self.item.set_paths(paths);
+        self.update_paths();
 
         Ok(())
     }
 
-    pub fn get_paths(&self) -> HashSet<PathBuf> {
-        let mut paths: HashSet<_> = self.item.get_paths().iter().cloned().collect();
+    /// Images of the category, including those of its sub categories.
+    pub fn get_paths(&self) -> &HashSet<PathBuf> {
+        &self.paths
+    }
+
+    /// Gathers the images of the category from its item and its sub
+    /// categories, which must be up to date.
+    fn update_paths(&mut self) {
+        self.paths = self.item.get_paths().iter().cloned().collect();
+        self.leafs
+            .iter()
+            .for_each(|leaf| self.paths.extend(leaf.paths.iter().cloned()));
+
+        self.nb_own = self
+            .paths
+            .iter()
+            .filter(|path| !self.leafs.iter().any(|leaf| leaf.paths.contains(*path)))
+            .count();
+    }
+
+    /// Gathers the images of every category of the tree, after their items
+    /// changed.
+    pub fn recount(&mut self) {
+        self.leafs.iter_mut().for_each(CategoryTree::recount);
+        self.update_paths();
+    }
+
+    /// Adds an image to a category of the tree, returns `false` if it isn't
+    /// one of them.
+    pub fn add_path(&mut self, category: &str, path: PathBuf) -> bool {
+        if self.item.name() == category {
+            if self.paths.insert(path.clone()) {
+                self.nb_own += 1;
+            }
+            self.item.add_path(path);
+
+            return true;
+        }
+
+        // The image moves from the category itself to a sub category
+        let was_own =
+            self.paths.contains(&path) && !self.leafs.iter().any(|leaf| leaf.paths.contains(&path));
 
+        for leaf in &mut self.leafs {
+            if leaf.add_path(category, path.clone()) {
+                if was_own {
+                    self.nb_own -= 1;
+                }
+                self.paths.insert(path);
+
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn counts(&self, depth: usize, counts: &mut Vec<CategoryCount>) {
+        counts.push(CategoryCount {
+            name: self.item.name().to_string(),
+            depth,
+            own: self.nb_own,
+            total: self.paths.len(),
+        });
         self.leafs
             .iter()
-            .for_each(|leaf| paths.extend(leaf.get_paths()));
-        paths
+            .for_each(|leaf| leaf.counts(depth + 1, counts));
     }
 
-    pub fn export_paths(&self, output_dir: &Path) -> Result<(), Box<dyn Error>> {
-        self.export_paths_inner(output_dir, &mut HashSet::new())
+    /// Writes a row per image, followed by its `metadata`.
+    pub fn export_paths(
+        &self,
+        output_dir: &Path,
+        metadata: &Metadata,
+    ) -> Result<(), Box<dyn Error>> {
+        self.export_paths_inner(output_dir, metadata, &mut HashSet::new())
     }
 
     fn export_paths_inner(
         &self,
         output_dir: &Path,
+        metadata: &Metadata,
         parent_paths: &mut HashSet<PathBuf>,
     ) -> Result<(), Box<dyn Error>> {
         let mut item_paths = self
@@ -63,10 +148,12 @@ impl CategoryTree {
 
         self.leafs
             .iter()
-            .try_for_each(|leaf| leaf.export_paths_inner(output_dir, &mut item_paths))?;
+            .try_for_each(|leaf| leaf.export_paths_inner(output_dir, metadata, &mut item_paths))?;
 
+        // Rows of the input may be longer than its headers
         let mut wdr = WriterBuilder::new()
             .has_headers(false)
+            .flexible(true)
             .from_path(self.item.make_category_path(output_dir))?;
 
         let item_paths = {
@@ -74,15 +161,41 @@ impl CategoryTree {
             item_paths.sort();
             item_paths
         };
+        if let Some(header) = metadata.header() {
+            wdr.write_record(header)?;
+        }
         for path in &item_paths {
-            wdr.serialize(&path)?;
+            let path_str = path.to_string_lossy();
+            wdr.write_record([path_str.as_ref()].into_iter().chain(metadata.row(path)))?;
         }
 
-        parent_paths.extend(item_paths.into_iter());
+        parent_paths.extend(item_paths);
 
         Ok(())
     }
 
+    pub fn for_each_item<F: FnMut(&CategoryTreeItem)>(&self, f: &mut F) {
+        f(&self.item);
+        self.leafs.iter().for_each(|leaf| leaf.for_each_item(f));
+    }
+
+    pub fn into_items(self, items: &mut Vec<CategoryTreeItem>) {
+        self.leafs
+            .into_iter()
+            .for_each(|leaf| leaf.into_items(items));
+        items.push(self.item);
+    }
+
+    pub fn find_by_name(&self, category: &str) -> Option<&CategoryTree> {
+        if self.item.name() == category {
+            Some(self)
+        } else {
+            self.leafs
+                .iter()
+                .find_map(|leaf| leaf.find_by_name(category))
+        }
+    }
+
     pub fn find_item_by_name(&mut self, category: &str) -> Option<&mut CategoryTreeItem> {
         if self.item.name() == category {
             Some(&mut self.item)
@@ -101,29 +214,123 @@ impl CategoryTree {
         }
     }
 
+    /// Whether the category, sub categories included, reached its quota.
+    pub fn is_full(&self) -> bool {
+        self.item
+            .quota()
+            .is_some_and(|quota| self.paths.len() >= quota)
+    }
+
+    /// Whether a category or one of its parents is full, `None` if it doesn't
+    /// exist.
+    pub fn is_blocked(&self, category: &str, parent_full: bool) -> Option<bool> {
+        let full = parent_full || self.is_full();
+
+        if self.item.name() == category {
+            Some(full)
+        } else {
+            self.leafs
+                .iter()
+                .find_map(|leaf| leaf.is_blocked(category, full))
+        }
+    }
+
+    /// Number of images which still fit in the quotas of a category and its
+    /// parents, `None` if it doesn't exist and `Some(None)` without quotas.
+    pub fn room(&self, category: &str, parent_room: Option<usize>) -> Option<Option<usize>> {
+        let own_room = self
+            .item
+            .quota()
+            .map(|quota| quota.saturating_sub(self.paths.len()));
+        let room = match (parent_room, own_room) {
+            (Some(parent_room), Some(own_room)) => Some(parent_room.min(own_room)),
+            (parent_room, own_room) => parent_room.or(own_room),
+        };
+
+        if self.item.name() == category {
+            Some(room)
+        } else {
+            self.leafs.iter().find_map(|leaf| leaf.room(category, room))
+        }
+    }
+
+    /// Whether every category with a quota is full, `None` if there is none.
+    pub fn are_quotas_met(&self) -> Option<bool> {
+        let mut leafs = self.leafs.iter().filter_map(|leaf| leaf.are_quotas_met());
+
+        match self.item.quota() {
+            Some(_) => Some(self.is_full() && leafs.all(|x| x)),
+            None => leafs.reduce(|acc, x| acc && x),
+        }
+    }
+
     pub fn update(
         &mut self,
         ctx: &egui::Context,
         ui: &mut egui::Ui,
         selected_cat: &mut Option<String>,
+        warning: &mut Option<String>,
+        parent_full: bool,
     ) {
-        let text = format!("{} ({:?})", self.item.name(), self.item.key());
+        let text = if self.item.is_key_auto_assigned() {
+            format!("{} ({:?}, auto)", self.item.name(), self.item.key())
+        } else {
+            format!("{} ({:?})", self.item.name(), self.item.key())
+        };
+
+        let count = self.paths.len();
+        let full = parent_full || self.is_full();
+        let badge = match self.item.quota() {
+            Some(quota) if self.is_full() => format!(" {}/{} full ", count, quota),
+            Some(quota) => format!(" {}/{} ", count, quota),
+            None => format!(" {} ", count),
+        };
+        let label = |ui: &mut egui::Ui| {
+            let clicked = ui
+                .add_enabled(!full, SelectableLabel::new(false, text))
+                .on_hover_text("Click to assign the current image")
+                .on_disabled_hover_text("This category is full")
+                .clicked();
+            ui.label(
+                RichText::new(badge)
+                    .small()
+                    .background_color(ui.visuals().widgets.inactive.bg_fill),
+            );
+
+            clicked
+        };
 
-        if !self.leafs.is_empty() {
-            CollapsingHeader::new(text)
-                .default_open(true)
-                .show(ui, |ui| {
+        let clicked = if !self.leafs.is_empty() {
+            let id = ui.make_persistent_id(self.item.name());
+            let (_, header, _) = CollapsingState::load_with_default_open(ctx, id, true)
+                .show_header(ui, label)
+                .body(|ui| {
                     self.leafs.iter_mut().for_each(|leaf| {
-                        leaf.update(ctx, ui, selected_cat);
+                        leaf.update(ctx, ui, selected_cat, warning, full);
                     });
                 });
+
+            header.inner
         } else {
-            ui.label(text);
-        }
+            ui.horizontal(label).inner
+        };
 
-        if ctx.input().key_pressed(self.item.key()) {
+        if clicked {
             *selected_cat = Some(self.item.name().to_string());
         }
+
+        // Shortcuts with Ctrl must not select categories
+        if shortcuts_enabled(ctx)
+            && !ctx.input().modifiers.command
+            && ctx.input().key_pressed(self.item.key())
+        {
+            if full {
+                *warning = Some(format!("\"{}\" is full", self.item.name()));
+            } else {
+                *selected_cat = Some(self.item.name().to_string());
+                *warning = None;
+            }
+        }
     }
 }
 
@@ -142,7 +349,17 @@ impl From<Category> for CategoryTree {
         };
 
         Self {
-            item: CategoryTreeItem::new(value.name, value.key),
+            paths: HashSet::new(),
+            nb_own: 0,
+            item: CategoryTreeItem::new(
+                value.name,
+                value
+                    .key
+                    .expect("Keys are assigned when loading the config"),
+                value.auto_key,
+                value.quota,
+                value.shape,
+            ),
             leafs,
         }
     }
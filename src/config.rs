@@ -1,5 +1,13 @@
+mod edit;
+mod format;
+mod include;
+mod keys;
+mod paths;
+mod validation;
+mod watcher;
+
 use egui::Key;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     convert::TryFrom,
@@ -8,119 +16,396 @@ use std::{
     path::{Path, PathBuf},
 };
 
+pub use edit::CategoryEdit;
+use format::Format;
+pub use keys::{find_free_key, parse_key, DIGITS};
+pub use watcher::ConfigWatcher;
+
+/// What is drawn for a category in regions mode.
+#[derive(Clone, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    #[default]
+    Box,
+    Polygon,
+    /// Named points placed in order
+    Keypoints(Vec<String>),
+}
+
 #[derive(Deserialize)]
 pub struct Category {
     pub name: String,
-    pub key: Key,
+    /// Assigned when loading the config if omitted
+    pub key: Option<Key>,
     pub sub_categories: Option<Vec<Category>>,
+    /// Number of images after which the category is full
+    pub quota: Option<usize>,
+    #[serde(default)]
+    pub shape: Shape,
+    #[serde(skip)]
+    pub auto_key: bool,
 }
 
-impl Category {
-    fn flattent_category(&self, result: &mut Vec<Category>) {
-        if let Some(sub_categories) = &self.sub_categories {
-            sub_categories
-                .iter()
-                .for_each(|x| x.flattent_category(result));
-        }
+/// Attribute recorded for each image besides its category.
+#[derive(Clone, Deserialize, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: AttributeKind,
+    /// Toggles a boolean or cycles through the values of an enum
+    pub key: Option<Key>,
+}
+
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AttributeKind {
+    Bool,
+    Enum { values: Vec<String> },
+    Int { min: Option<i64>, max: Option<i64> },
+}
+
+/// Notes and tags written on images.
+#[derive(Clone, Deserialize, PartialEq)]
+pub struct Notes {
+    /// Toggled with Ctrl and their position, from 1 to 9
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Opens the note of the current image, pressed with Ctrl
+    #[serde(default = "Notes::default_key")]
+    pub key: Key,
+}
 
-        result.push(Category {
-            name: self.name.clone(),
-            key: self.key,
-            sub_categories: None,
-        });
+impl Notes {
+    fn default_key() -> Key {
+        Key::N
     }
 }
 
-#[derive(Deserialize)]
+impl Default for Notes {
+    fn default() -> Self {
+        Self {
+            tags: vec![],
+            key: Self::default_key(),
+        }
+    }
+}
+
+/// Tried in order, `Csv` first as it also has a `root`.
+#[derive(Clone, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub enum Input {
-    Dir { root: PathBuf },
-    Csv { ds: PathBuf, root: Option<PathBuf> },
+    Csv {
+        ds: PathBuf,
+        root: Option<PathBuf>,
+        #[serde(default)]
+        has_headers: bool,
+        #[serde(default)]
+        path_column: Column,
+        caption_column: Option<Column>,
+        /// `,` when omitted
+        delimiter: Option<char>,
+    },
+    Dir {
+        root: PathBuf,
+    },
 }
 
-#[derive(Deserialize)]
-pub struct Config {
-    pub input: Input,
-    pub output_dir: PathBuf,
-    pub categories: Vec<Category>,
+impl Input {
+    /// Directory of the images, or CSV file listing them.
+    pub fn path(&self) -> PathBuf {
+        match self {
+            Self::Dir { root } => root.clone(),
+            Self::Csv { ds, root, .. } => match root {
+                Some(root) => root.join(ds),
+                None => ds.clone(),
+            },
+        }
+    }
+
+    /// Directory of the images, or of the CSV file listing them.
+    pub fn root(&self) -> PathBuf {
+        match self {
+            Self::Dir { root } => root.clone(),
+            Self::Csv {
+                root: Some(root), ..
+            } => root.clone(),
+            Self::Csv { ds, root: None, .. } => {
+                ds.parent().map(Path::to_path_buf).unwrap_or_default()
+            }
+        }
+    }
 }
 
-impl Config {
-    fn flatten_categories(&self) -> Vec<Category> {
-        let mut categories = vec![];
+/// Column of a CSV file, by name when it has headers or by index.
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
 
-        self.categories
-            .iter()
-            .for_each(|x| x.flattent_category(&mut categories));
+impl Default for Column {
+    fn default() -> Self {
+        Self::Index(0)
+    }
+}
 
-        categories
+impl Column {
+    /// Index of the column among `headers`.
+    pub fn index(&self, headers: Option<&csv::StringRecord>) -> Result<usize, ConfigError> {
+        match self {
+            Self::Index(i) => Ok(*i),
+            Self::Name(name) => headers
+                .and_then(|x| x.iter().position(|x| x == name))
+                .ok_or_else(|| ConfigError::UnknownColumn(name.clone())),
+        }
     }
+}
 
-    fn check_uniqueness<T, U, F, G>(
-        categories: &[Category],
-        f: F,
-        g: G,
-    ) -> Result<(), Box<dyn Error>>
-    where
-        F: Fn(&Category, &mut HashMap<T, U>),
-        G: Fn(HashMap<T, U>) -> Result<(), Box<dyn Error>>,
-    {
-        let bindings = {
-            let mut bindings: HashMap<T, U> = HashMap::new();
+/// Random subset of the remaining images to label.
+#[derive(Clone, Deserialize, PartialEq, Serialize)]
+pub struct Sample {
+    pub size: Option<usize>,
+    pub fraction: Option<f64>,
+    /// Random when omitted
+    pub seed: Option<u64>,
+    /// Samples each input subdirectory separately
+    #[serde(default)]
+    pub stratified: bool,
+}
 
-            categories.iter().for_each(|x| {
-                f(x, &mut bindings);
-            });
+/// Model predictions to verify, read from a headerless `path,category,score`
+/// CSV.
+#[derive(Clone, Deserialize, PartialEq)]
+pub struct Predictions {
+    pub path: PathBuf,
+    #[serde(default = "Predictions::default_accept_key")]
+    pub accept_key: Key,
+}
 
-            bindings
-        };
+impl Predictions {
+    fn default_accept_key() -> Key {
+        Key::Enter
+    }
+}
 
-        g(bindings)
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// Lowest highest probability first
+    LeastConfidence,
+    /// Highest entropy first
+    Entropy,
+    /// Smallest gap between the two highest probabilities first
+    Margin,
+}
+
+/// Orders the images by model uncertainty, read from a headerless CSV with a
+/// path followed by the class probabilities on each row.
+#[derive(Clone, Deserialize, PartialEq)]
+pub struct Queue {
+    pub scores: PathBuf,
+    pub strategy: Strategy,
+    /// Number of images labeled between two reloads of the scores
+    #[serde(default = "Queue::default_batch_size")]
+    pub batch_size: usize,
+}
+
+impl Queue {
+    fn default_batch_size() -> usize {
+        50
     }
+}
 
-    fn check_name_uniqueness(categories: &[Category]) -> Result<(), Box<dyn Error>> {
-        Self::check_uniqueness(
-            categories,
-            |x, bindings| {
-                if let Some(binding) = bindings.get_mut(&x.name) {
-                    *binding += 1;
-                } else {
-                    bindings.insert(x.name.clone(), 1);
-                }
-            },
-            |bindings| {
-                if let Some(binding) = bindings.into_iter().filter(|x| x.1 > 1).take(1).next() {
-                    Err(Box::new(ConfigError::DuplicateName(binding)))
-                } else {
-                    Ok(())
-                }
-            },
-        )
+/// Local ONNX image classifier suggesting categories.
+#[derive(Clone, Deserialize, PartialEq)]
+pub struct Model {
+    pub path: PathBuf,
+    /// Name of each output of the model, in order
+    pub labels: Vec<String>,
+    /// Category suggested for each label, the label itself if omitted
+    #[serde(default)]
+    pub categories: HashMap<String, String>,
+    /// Width and height of the model input
+    #[serde(default = "Model::default_input_size")]
+    pub input_size: [usize; 2],
+    #[serde(default = "Model::default_mean")]
+    pub mean: [f32; 3],
+    #[serde(default = "Model::default_std")]
+    pub std: [f32; 3],
+    /// Whether the outputs are logits rather than probabilities
+    #[serde(default)]
+    pub softmax: bool,
+    /// One key per suggestion shown
+    #[serde(default = "Model::default_accept_keys")]
+    pub accept_keys: Vec<Key>,
+}
+
+impl Model {
+    fn default_input_size() -> [usize; 2] {
+        [224, 224]
     }
 
-    fn check_key_uniqueness(categories: &[Category]) -> Result<(), Box<dyn Error>> {
-        Self::check_uniqueness::<Key, Vec<String>, _, _>(
-            categories,
-            |x, bindings| {
-                if let Some(binding) = bindings.get_mut(&x.key) {
-                    binding.push(x.name.clone());
-                } else {
-                    bindings.insert(x.key, vec![x.name.clone()]);
-                }
-            },
-            |bindings| {
-                if let Some(binding) = bindings
+    fn default_mean() -> [f32; 3] {
+        [0.485, 0.456, 0.406]
+    }
+
+    fn default_std() -> [f32; 3] {
+        [0.229, 0.224, 0.225]
+    }
+
+    fn default_accept_keys() -> Vec<Key> {
+        vec![Key::Num1, Key::Num2, Key::Num3]
+    }
+}
+
+/// Pairs of images to compare.
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Pairs {
+    /// Every pair of images
+    All,
+    /// Random pairs of images
+    Random { count: usize, seed: Option<u64> },
+    /// Headerless `left,right` CSV
+    Csv(PathBuf),
+}
+
+/// How images are shown and labeled.
+#[derive(Clone, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// One image at a time
+    #[default]
+    Single,
+    /// Several images at a time, labeled together
+    Grid {
+        #[serde(default = "Mode::default_grid_size")]
+        size: usize,
+    },
+    /// Two images at a time, the better one is chosen
+    Pairwise { pairs: Pairs },
+    /// Categories are assigned to regions drawn on the image
+    Regions,
+    /// Images are given a score instead of a category
+    Rating {
+        #[serde(default = "Mode::default_rating_min")]
+        min: u32,
+        #[serde(default = "Mode::default_rating_max")]
+        max: u32,
+        /// Shift with a digit adds half a point
+        #[serde(default)]
+        half_steps: bool,
+    },
+}
+
+impl Mode {
+    fn default_grid_size() -> usize {
+        12
+    }
+
+    fn default_rating_min() -> u32 {
+        1
+    }
+
+    fn default_rating_max() -> u32 {
+        5
+    }
+
+    /// Keys used by the mode, with what they do.
+    pub fn reserved_keys(&self) -> Vec<(Key, &'static str)> {
+        match self {
+            Self::Single => vec![],
+            Self::Grid { .. } => vec![
+                (Key::Space, "toggling the selection"),
+                (Key::ArrowLeft, "moving the cursor"),
+                (Key::ArrowRight, "moving the cursor"),
+                (Key::ArrowUp, "moving the cursor"),
+                (Key::ArrowDown, "moving the cursor"),
+            ],
+            Self::Pairwise { .. } => vec![
+                (Key::ArrowLeft, "choosing the left image"),
+                (Key::ArrowRight, "choosing the right image"),
+                (Key::ArrowDown, "choosing a tie"),
+            ],
+            Self::Regions => vec![
+                (Key::Enter, "finishing the image"),
+                (Key::Backspace, "removing the last region"),
+                (Key::Escape, "cancelling the region being drawn"),
+            ],
+            Self::Rating { min, max, .. } => {
+                // Digits typed in any of the scores
+                let mut digits = (*min..=*max)
+                    .flat_map(|x| x.to_string().into_bytes())
+                    .map(|x| (x - b'0') as usize)
+                    .collect::<Vec<_>>();
+                digits.sort_unstable();
+                digits.dedup();
+
+                let mut keys = digits
                     .into_iter()
-                    .filter(|x| x.1.len() > 1)
-                    .take(1)
-                    .next()
-                {
-                    Err(Box::new(ConfigError::DuplicateBindings(binding)))
-                } else {
-                    Ok(())
+                    .map(|x| (DIGITS[x], "rating"))
+                    .collect::<Vec<_>>();
+                if *max >= 10 {
+                    keys.push((Key::Enter, "confirming a rating"));
                 }
-            },
-        )
+                keys
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub input: Input,
+    pub output_dir: PathBuf,
+    pub categories: Vec<Category>,
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+    #[serde(default)]
+    pub notes: Notes,
+    /// Ends the session once every category with a quota is full
+    #[serde(default)]
+    pub stop_when_quotas_met: bool,
+    pub sample: Option<Sample>,
+    pub predictions: Option<Predictions>,
+    pub queue: Option<Queue>,
+    pub model: Option<Model>,
+    #[serde(default)]
+    pub mode: Mode,
+    /// Files the config was read from, starting with the config file itself
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>,
+}
+
+impl Config {
+    /// Keys bound to something else than a category.
+    pub fn reserved_keys(&self) -> Vec<Key> {
+        // Predictions and suggestions are only shown in single mode
+        let is_single = matches!(self.mode, Mode::Single);
+
+        self.predictions
+            .iter()
+            .map(|x| x.accept_key)
+            .chain(self.model.iter().flat_map(|x| x.accept_keys.clone()))
+            .filter(|_| is_single)
+            .chain(self.mode.reserved_keys().into_iter().map(|x| x.0))
+            .chain(self.attributes.iter().filter_map(|x| x.key))
+            .collect()
+    }
+
+    /// Config files looked up in the current directory, in order.
+    const DEFAULT_PATHS: [&'static str; 4] = [
+        "./config.json",
+        "./config.toml",
+        "./config.yaml",
+        "./config.yml",
+    ];
+
+    pub fn default_path() -> &'static Path {
+        let paths = Self::DEFAULT_PATHS.map(Path::new);
+
+        paths.iter().find(|x| x.exists()).unwrap_or(&paths[0])
     }
 }
 
@@ -128,12 +413,19 @@ impl TryFrom<&Path> for Config {
     type Error = Box<dyn Error>;
 
     fn try_from(value: &Path) -> Result<Self, Self::Error> {
+        let format = Format::from_path(value)?;
         let data = std::fs::read_to_string(value)?;
-        let config: Config = serde_json::from_str(&data)?;
+        let mut data = format.parse(&data)?;
+        let mut issues = vec![];
+
+        let taxonomies = include::resolve(&mut data, value, &mut issues);
+        paths::resolve(&mut data, value, &mut issues);
+
+        let mut config = validation::validate(data, issues)?;
+        config.sources = std::iter::once(value.to_path_buf())
+            .chain(taxonomies)
+            .collect();
 
-        let categories = config.flatten_categories();
-        Self::check_name_uniqueness(&categories)?;
-        Self::check_key_uniqueness(&categories)?;
         Ok(config)
     }
 }
@@ -141,6 +433,27 @@ impl TryFrom<&Path> for Config {
 pub enum ConfigError {
     DuplicateBindings((Key, Vec<String>)),
     DuplicateName((String, usize)),
+    InvalidKey(String),
+    EmptyCategories,
+    MissingPath(PathBuf),
+    UnwritableOutputDir((PathBuf, String)),
+    Malformed(String),
+    UnsupportedFormat(PathBuf),
+    IncludeCycle(PathBuf),
+    UnknownCategory(String),
+    UndefinedVariable(String),
+    NoFreeKey(String),
+    InvalidQuota(String),
+    InvalidSample(String),
+    MissingFeature(String),
+    InvalidRating(String),
+    InvalidAttribute((String, String)),
+    InvalidTag(String),
+    UnknownColumn(String),
+    InvalidColumns(String),
+    UneditableFormat(PathBuf),
+    IncludedCategory(String),
+    ReservedName((String, String)),
 }
 
 impl Error for ConfigError {}
@@ -156,7 +469,7 @@ impl Display for ConfigError {
         match self {
             Self::DuplicateBindings(e) => {
                 writeln!(f, "Duplicate bindings {:?}", e.0)?;
-                writeln!(f, "Due to categories:")?;
+                writeln!(f, "Bound to:")?;
                 write!(
                     f,
                     "{}",
@@ -167,6 +480,52 @@ impl Display for ConfigError {
             Self::DuplicateName(e) => {
                 write!(f, "Duplicate category name, got {} times \"{}\"", e.1, e.0)
             }
+            Self::InvalidKey(e) => write!(f, "Invalid key binding {}", e),
+            Self::EmptyCategories => write!(f, "Category list is empty"),
+            Self::MissingPath(e) => write!(f, "{} doesn't exist", e.display()),
+            Self::UnwritableOutputDir(e) => {
+                write!(
+                    f,
+                    "Output directory {} isn't writable, {}",
+                    e.0.display(),
+                    e.1
+                )
+            }
+            Self::Malformed(e) => write!(f, "{}", e),
+            Self::UnsupportedFormat(e) => write!(
+                f,
+                "Unsupported config format {}, expected .json, .toml, .yaml or .yml",
+                e.display()
+            ),
+            Self::IncludeCycle(e) => write!(f, "{} is included by itself", e.display()),
+            Self::UnknownCategory(e) => write!(f, "No category named \"{}\"", e),
+            Self::UndefinedVariable(e) => write!(f, "Environment variable {} isn't set", e),
+            Self::NoFreeKey(e) => write!(f, "No free key left for category \"{}\"", e),
+            Self::InvalidQuota(e) => write!(f, "Quota of category \"{}\" must be positive", e),
+            Self::InvalidSample(e) => write!(f, "Invalid sample, {}", e),
+            Self::MissingFeature(e) => {
+                write!(f, "Requires building with the `{}` feature", e)
+            }
+            Self::InvalidRating(e) => write!(f, "Invalid rating scale, {}", e),
+            Self::InvalidAttribute(e) => write!(f, "Invalid attribute \"{}\", {}", e.0, e.1),
+            Self::InvalidTag(e) => write!(f, "Tag \"{}\" can't be empty or contain `;`", e),
+            Self::UnknownColumn(e) => write!(f, "No column named \"{}\" in the CSV input", e),
+            Self::InvalidColumns(e) => write!(f, "Invalid CSV input, {}", e),
+            Self::UneditableFormat(e) => write!(
+                f,
+                "{} can't be edited without losing its comments, edit it by hand",
+                e.display()
+            ),
+            Self::IncludedCategory(e) => write!(
+                f,
+                "Category \"{}\" comes from a taxonomy file, add its sub categories there",
+                e
+            ),
+            Self::ReservedName(e) => write!(
+                f,
+                "Category \"{}\" would be exported over {}, rename it",
+                e.0, e.1
+            ),
         }
     }
 }
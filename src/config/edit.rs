@@ -0,0 +1,571 @@
+mod document;
+
+use egui::Key;
+use serde_json::{json, Value};
+use std::{error::Error, path::Path};
+
+use super::{format::Format, include, ConfigError};
+
+/// A change made to the categories from the GUI.
+pub enum CategoryEdit {
+    Add {
+        name: String,
+        key: Key,
+        parent: Option<String>,
+    },
+    Rename {
+        name: String,
+        new_name: String,
+    },
+    Rebind {
+        name: String,
+        key: Key,
+    },
+}
+
+/// A change to the config file, at a JSON pointer.
+enum Change {
+    /// Sets `key` of the object at `pointer`
+    Set {
+        pointer: String,
+        key: String,
+        value: Value,
+    },
+    /// Appends to the array at `pointer`
+    Push { pointer: String, value: Value },
+}
+
+impl CategoryEdit {
+    /// Name of the category the edit applies to, if any.
+    fn target(&self) -> Option<&str> {
+        match self {
+            Self::Add { parent, .. } => parent.as_deref(),
+            Self::Rename { name, .. } | Self::Rebind { name, .. } => Some(name),
+        }
+    }
+
+    /// Writes the edit to the config file. Categories of included taxonomies
+    /// are edited with an override, their files are left untouched.
+    pub fn write(&self, config_path: &Path) -> Result<(), Box<dyn Error>> {
+        let format = Format::from_path(config_path)?;
+        // Comments can't be kept when writing YAML
+        if matches!(format, Format::Yaml) {
+            return Err(Box::new(ConfigError::UneditableFormat(
+                config_path.to_path_buf(),
+            )));
+        }
+
+        let data = std::fs::read_to_string(config_path)?;
+        let mut config = format.parse(&data)?;
+
+        let change = match (self, config.get("categories")) {
+            (
+                Self::Add {
+                    name,
+                    key,
+                    parent: None,
+                },
+                categories,
+            ) => Some(push_category(
+                categories,
+                "",
+                "categories",
+                new_category(name, *key),
+            )),
+            (_, Some(categories)) => {
+                self.edit_categories(categories, "", "categories", config_path)?
+            }
+            (_, None) => None,
+        };
+        let change = change.ok_or_else(|| {
+            ConfigError::UnknownCategory(self.target().unwrap_or_default().to_string())
+        })?;
+
+        let data = match format {
+            Format::Toml => document::apply(&data, &change)?,
+            _ => {
+                change.apply(&mut config)?;
+                format.serialize(&config)?
+            }
+        };
+        std::fs::write(config_path, data)?;
+
+        Ok(())
+    }
+
+    /// Finds the change for the category list at `key` of the object at
+    /// `parent`.
+    fn edit_categories(
+        &self,
+        categories: &Value,
+        parent: &str,
+        key: &str,
+        config_path: &Path,
+    ) -> Result<Option<Change>, Box<dyn Error>> {
+        let pointer = format!("{}/{}", parent, key);
+
+        match categories {
+            Value::String(_) | Value::Object(_) => {
+                self.edit_reference(categories, parent, key, config_path)
+            }
+            Value::Array(categories) => {
+                for (i, category) in categories.iter().enumerate() {
+                    let change = if category.get("include").is_some() {
+                        self.edit_reference(category, &pointer, &i.to_string(), config_path)?
+                    } else {
+                        let pointer = format!("{}/{}", pointer, i);
+                        self.edit_category(category, &pointer, config_path)?
+                    };
+
+                    if change.is_some() {
+                        return Ok(change);
+                    }
+                }
+
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn edit_category(
+        &self,
+        category: &Value,
+        pointer: &str,
+        config_path: &Path,
+    ) -> Result<Option<Change>, Box<dyn Error>> {
+        if category.get("name").and_then(Value::as_str) == self.target() {
+            let change = match self {
+                Self::Add { name, key, .. } => push_category(
+                    category.get("sub_categories"),
+                    pointer,
+                    "sub_categories",
+                    new_category(name, *key),
+                ),
+                Self::Rename { new_name, .. } => Change::set(pointer, "name", json!(new_name)),
+                Self::Rebind { key, .. } => Change::set(pointer, "key", json!(key)),
+            };
+
+            return Ok(Some(change));
+        }
+
+        match category.get("sub_categories") {
+            Some(sub_categories) => {
+                self.edit_categories(sub_categories, pointer, "sub_categories", config_path)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Edits the overrides of the taxonomy reference at `key` of `parent`,
+    /// adding one for a category of the taxonomy.
+    fn edit_reference(
+        &self,
+        reference: &Value,
+        parent: &str,
+        key: &str,
+        config_path: &Path,
+    ) -> Result<Option<Change>, Box<dyn Error>> {
+        let pointer = format!("{}/{}", parent, key);
+
+        if let Some(Value::Array(overrides)) = reference.get("overrides") {
+            for (i, category) in overrides.iter().enumerate() {
+                let pointer = format!("{}/overrides/{}", pointer, i);
+                let name = category.get("rename").or_else(|| category.get("name"));
+
+                if name.and_then(Value::as_str) == self.target() {
+                    return self.edit_override(category, &pointer).map(Some);
+                }
+                // Sub categories of an override are defined in the config
+                if let Some(sub_categories) = category.get("sub_categories") {
+                    let change = self.edit_categories(
+                        sub_categories,
+                        &pointer,
+                        "sub_categories",
+                        config_path,
+                    )?;
+                    if change.is_some() {
+                        return Ok(change);
+                    }
+                }
+            }
+        }
+
+        if !self.is_included(reference, config_path) {
+            return Ok(None);
+        }
+        let category = match self {
+            Self::Add { .. } => {
+                return Err(Box::new(ConfigError::IncludedCategory(
+                    self.target().unwrap_or_default().to_string(),
+                )))
+            }
+            Self::Rename { name, new_name } => json!({ "name": name, "rename": new_name }),
+            Self::Rebind { name, key } => json!({ "name": name, "key": key }),
+        };
+
+        Ok(Some(match reference {
+            Value::String(path) => Change::set(
+                parent,
+                key,
+                json!({ "include": path, "overrides": [category] }),
+            ),
+            _ => push_category(reference.get("overrides"), &pointer, "overrides", category),
+        }))
+    }
+
+    /// Edits an override of the target category.
+    fn edit_override(&self, category: &Value, pointer: &str) -> Result<Change, Box<dyn Error>> {
+        match self {
+            // Overridden sub categories replace the included ones
+            Self::Add { name, key, .. } if category.get("sub_categories").is_some() => {
+                Ok(push_category(
+                    category.get("sub_categories"),
+                    pointer,
+                    "sub_categories",
+                    new_category(name, *key),
+                ))
+            }
+            Self::Add { .. } => Err(Box::new(ConfigError::IncludedCategory(
+                self.target().unwrap_or_default().to_string(),
+            ))),
+            Self::Rename { new_name, .. } => Ok(Change::set(pointer, "rename", json!(new_name))),
+            Self::Rebind { key, .. } => Ok(Change::set(pointer, "key", json!(key))),
+        }
+    }
+
+    /// Whether the target category comes from the referenced taxonomy.
+    fn is_included(&self, reference: &Value, config_path: &Path) -> bool {
+        let mut config = json!({ "categories": reference });
+        include::resolve(&mut config, config_path, &mut vec![]);
+
+        self.target()
+            .is_some_and(|name| contains_category(&config["categories"], name))
+    }
+}
+
+impl Change {
+    fn set(pointer: &str, key: &str, value: Value) -> Self {
+        Self::Set {
+            pointer: pointer.to_string(),
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    fn apply(self, config: &mut Value) -> Result<(), ConfigError> {
+        match self {
+            Self::Set {
+                pointer,
+                key,
+                value,
+            } => match config.pointer_mut(&pointer) {
+                Some(Value::Object(object)) => {
+                    object.insert(key, value);
+                }
+                _ => {
+                    return Err(ConfigError::Malformed(format!(
+                        "{} isn't an object",
+                        pointer
+                    )))
+                }
+            },
+            Self::Push { pointer, value } => match config.pointer_mut(&pointer) {
+                Some(Value::Array(array)) => array.push(value),
+                _ => return Err(ConfigError::Malformed(format!("{} isn't a list", pointer))),
+            },
+        }
+
+        Ok(())
+    }
+}
+
+fn new_category(name: &str, key: Key) -> Value {
+    json!({ "name": name, "key": key })
+}
+
+/// Appends a category to the list at `key` of `parent`, turning a taxonomy
+/// reference into a list including it.
+fn push_category(categories: Option<&Value>, parent: &str, key: &str, category: Value) -> Change {
+    match categories {
+        Some(Value::Array(_)) => Change::Push {
+            pointer: format!("{}/{}", parent, key),
+            value: category,
+        },
+        Some(Value::String(path)) => {
+            Change::set(parent, key, json!([{ "include": path }, category]))
+        }
+        Some(reference) => Change::set(parent, key, json!([reference, category])),
+        None => Change::set(parent, key, json!([category])),
+    }
+}
+
+fn contains_category(categories: &Value, name: &str) -> bool {
+    categories.as_array().is_some_and(|categories| {
+        categories.iter().any(|category| {
+            category.get("name").and_then(Value::as_str) == Some(name)
+                || category
+                    .get("sub_categories")
+                    .is_some_and(|x| contains_category(x, name))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::Key;
+    use serde_json::{json, Value};
+    use std::path::{Path, PathBuf};
+
+    use super::CategoryEdit;
+    use crate::config::{format::Format, ConfigError};
+
+    /// Writes `files` to a directory of their own, the first being the config.
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("image_picker_edit_{}", test));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        files
+            .iter()
+            .for_each(|(name, data)| std::fs::write(dir.join(name), data).unwrap());
+
+        dir.join(files[0].0)
+    }
+
+    fn read_config(path: &Path) -> Value {
+        let data = std::fs::read_to_string(path).unwrap();
+        Format::from_path(path).unwrap().parse(&data).unwrap()
+    }
+
+    fn add(name: &str, key: Key, parent: Option<&str>) -> CategoryEdit {
+        CategoryEdit::Add {
+            name: name.to_string(),
+            key,
+            parent: parent.map(String::from),
+        }
+    }
+
+    fn rename(name: &str, new_name: &str) -> CategoryEdit {
+        CategoryEdit::Rename {
+            name: name.to_string(),
+            new_name: new_name.to_string(),
+        }
+    }
+
+    fn rebind(name: &str, key: Key) -> CategoryEdit {
+        CategoryEdit::Rebind {
+            name: name.to_string(),
+            key,
+        }
+    }
+
+    const JSON_CONFIG: &str = r#"{
+        "output_dir": "out",
+        "categories": [
+            { "name": "fruit", "key": "F", "sub_categories": [{ "name": "apple" }] },
+            { "name": "vegetable", "key": "V" }
+        ]
+    }"#;
+
+    #[test]
+    fn json_edits_nested_categories() {
+        let path = write_files("json_nested", &[("config.json", JSON_CONFIG)]);
+
+        add("pear", Key::P, Some("fruit")).write(&path).unwrap();
+        add("green", Key::G, Some("apple")).write(&path).unwrap();
+        add("herb", Key::H, None).write(&path).unwrap();
+        rename("apple", "red apple").write(&path).unwrap();
+        rebind("green", Key::N).write(&path).unwrap();
+
+        let config = read_config(&path);
+        assert_eq!(config["output_dir"], "out");
+        assert_eq!(
+            config["categories"],
+            json!([
+                {
+                    "name": "fruit",
+                    "key": "F",
+                    "sub_categories": [
+                        {
+                            "name": "red apple",
+                            "sub_categories": [{ "name": "green", "key": "N" }]
+                        },
+                        { "name": "pear", "key": "P" }
+                    ]
+                },
+                { "name": "vegetable", "key": "V" },
+                { "name": "herb", "key": "H" }
+            ])
+        );
+    }
+
+    #[test]
+    fn unknown_category_is_an_error() {
+        let path = write_files("unknown", &[("config.json", JSON_CONFIG)]);
+
+        let error = rename("pear", "nashi").write(&path).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ConfigError>(),
+            Some(ConfigError::UnknownCategory(name)) if name == "pear"
+        ));
+    }
+
+    const TOML_CONFIG: &str = r#"# Labeling of the market photos
+output_dir = "out" # next to the config
+
+[[categories]]
+name = "fruit" # sold by weight
+key = "F"
+
+# Kept apart from the rest
+[[categories.sub_categories]]
+name = "apple"
+key = "A" # the first one
+
+[[categories]]
+name = "vegetable"
+key = "V"
+sub_categories = [{ name = "leek" }]
+"#;
+
+    #[test]
+    fn toml_edits_keep_comments() {
+        let path = write_files("toml", &[("config.toml", TOML_CONFIG)]);
+
+        add("herb", Key::H, None).write(&path).unwrap();
+        add("pear", Key::P, Some("fruit")).write(&path).unwrap();
+        add("green", Key::G, Some("apple")).write(&path).unwrap();
+        add("onion", Key::O, Some("vegetable"))
+            .write(&path)
+            .unwrap();
+        rename("apple", "red apple").write(&path).unwrap();
+        rebind("apple", Key::R).write(&path).unwrap_err();
+        rebind("red apple", Key::R).write(&path).unwrap();
+        rebind("leek", Key::L).write(&path).unwrap();
+
+        let data = std::fs::read_to_string(&path).unwrap();
+        for comment in [
+            "# Labeling of the market photos",
+            "# next to the config",
+            "# sold by weight",
+            "# Kept apart from the rest",
+            "# the first one",
+        ] {
+            assert!(
+                data.contains(comment),
+                "{} is missing from\n{}",
+                comment,
+                data
+            );
+        }
+
+        let config = read_config(&path);
+        assert_eq!(config["output_dir"], "out");
+        assert_eq!(
+            config["categories"],
+            json!([
+                {
+                    "name": "fruit",
+                    "key": "F",
+                    "sub_categories": [
+                        {
+                            "name": "red apple",
+                            "key": "R",
+                            "sub_categories": [{ "name": "green", "key": "G" }]
+                        },
+                        { "name": "pear", "key": "P" }
+                    ]
+                },
+                {
+                    "name": "vegetable",
+                    "key": "V",
+                    "sub_categories": [
+                        { "name": "leek", "key": "L" },
+                        { "name": "onion", "key": "O" }
+                    ]
+                },
+                { "name": "herb", "key": "H" }
+            ])
+        );
+    }
+
+    const TAXONOMY: &str = r#"[
+        { "name": "animal", "key": "A", "sub_categories": [{ "name": "cat", "key": "C" }] },
+        { "name": "plant", "key": "P" }
+    ]"#;
+
+    #[test]
+    fn included_categories_are_edited_with_overrides() {
+        let path = write_files(
+            "include_json",
+            &[
+                (
+                    "config.json",
+                    r#"{ "categories": [{ "include": "taxonomy.json" }, { "name": "other" }] }"#,
+                ),
+                ("taxonomy.json", TAXONOMY),
+            ],
+        );
+
+        rename("cat", "kitten").write(&path).unwrap();
+        rebind("kitten", Key::K).write(&path).unwrap();
+        rebind("plant", Key::L).write(&path).unwrap();
+        add("leaf", Key::E, Some("other")).write(&path).unwrap();
+
+        let error = add("dog", Key::D, Some("animal")).write(&path).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ConfigError>(),
+            Some(ConfigError::IncludedCategory(name)) if name == "animal"
+        ));
+
+        let dir = path.parent().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("taxonomy.json")).unwrap(),
+            TAXONOMY
+        );
+        assert_eq!(
+            read_config(&path)["categories"],
+            json!([
+                {
+                    "include": "taxonomy.json",
+                    "overrides": [
+                        { "name": "cat", "rename": "kitten", "key": "K" },
+                        { "name": "plant", "key": "L" }
+                    ]
+                },
+                { "name": "other", "sub_categories": [{ "name": "leaf", "key": "E" }] }
+            ])
+        );
+    }
+
+    #[test]
+    fn toml_taxonomy_reference_becomes_an_include() {
+        let path = write_files(
+            "include_toml",
+            &[
+                (
+                    "config.toml",
+                    "# Shared taxonomy\ncategories = \"taxonomy.json\" # see the wiki\n",
+                ),
+                ("taxonomy.json", TAXONOMY),
+            ],
+        );
+
+        rename("plant", "tree").write(&path).unwrap();
+        add("rock", Key::R, None).write(&path).unwrap();
+
+        let data = std::fs::read_to_string(&path).unwrap();
+        assert!(data.contains("# Shared taxonomy"), "{}", data);
+        assert_eq!(
+            read_config(&path)["categories"],
+            json!([
+                {
+                    "include": "taxonomy.json",
+                    "overrides": [{ "name": "plant", "rename": "tree" }]
+                },
+                { "name": "rock", "key": "R" }
+            ])
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use serde_json::Value;
+use std::error::Error;
+use toml_edit::{DocumentMut, InlineTable, Item};
+
+use super::Change;
+use crate::config::ConfigError;
+
+/// Applies a change to a TOML config, keeping its comments and formatting.
+pub fn apply(data: &str, change: &Change) -> Result<String, Box<dyn Error>> {
+    let mut document = data.parse::<DocumentMut>()?;
+
+    match change {
+        Change::Set {
+            pointer,
+            key,
+            value,
+        } => {
+            let item = pointer_mut(document.as_item_mut(), pointer)
+                .filter(|x| x.is_table_like())
+                .ok_or_else(|| ConfigError::Malformed(format!("{} isn't an object", pointer)))?;
+            let mut value = to_toml(value)?;
+
+            match item.get(key.as_str()).and_then(Item::as_value) {
+                // Keeps the comment after the replaced value
+                Some(previous) => *value.decor_mut() = previous.decor().clone(),
+                None => value = value.decorated(" ", ""),
+            }
+            match item {
+                Item::Value(toml_edit::Value::InlineTable(table)) => {
+                    table.insert(key, value);
+                    table.fmt();
+                }
+                item => {
+                    item[key.as_str()] = Item::Value(value);
+                }
+            }
+        }
+        Change::Push { pointer, value } => {
+            match (
+                pointer_mut(document.as_item_mut(), pointer),
+                to_toml(value)?,
+            ) {
+                // `[[categories]]` tables
+                (Some(Item::ArrayOfTables(tables)), toml_edit::Value::InlineTable(table)) => {
+                    tables.push(table.into_table());
+                }
+                (Some(Item::Value(toml_edit::Value::Array(array))), value) => array.push(value),
+                _ => {
+                    return Err(Box::new(ConfigError::Malformed(format!(
+                        "{} isn't a list",
+                        pointer
+                    ))))
+                }
+            }
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+fn pointer_mut<'a>(item: &'a mut Item, pointer: &str) -> Option<&'a mut Item> {
+    pointer
+        .split('/')
+        .skip(1)
+        .try_fold(item, |item, segment| match segment.parse::<usize>() {
+            Ok(i) => item.get_mut(i),
+            Err(_) => item.get_mut(segment),
+        })
+}
+
+fn to_toml(value: &Value) -> Result<toml_edit::Value, ConfigError> {
+    Ok(match value {
+        Value::Bool(value) => (*value).into(),
+        Value::Number(value) => match value.as_i64() {
+            Some(value) => value.into(),
+            None => value.as_f64().unwrap_or_default().into(),
+        },
+        Value::String(value) => value.as_str().into(),
+        Value::Array(values) => values
+            .iter()
+            .map(to_toml)
+            .collect::<Result<toml_edit::Array, _>>()?
+            .into(),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| Ok((key.as_str(), to_toml(value)?)))
+            .collect::<Result<InlineTable, _>>()?
+            .into(),
+        Value::Null => return Err(ConfigError::Malformed("TOML has no null".to_string())),
+    })
+}
@@ -0,0 +1,38 @@
+use serde_json::Value;
+use std::{error::Error, path::Path};
+
+use super::ConfigError;
+
+/// Supported config file formats, all of them sharing the JSON schema.
+pub enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("json") => Ok(Self::Json),
+            Some("toml") => Ok(Self::Toml),
+            Some("yaml" | "yml") => Ok(Self::Yaml),
+            _ => Err(ConfigError::UnsupportedFormat(path.to_path_buf())),
+        }
+    }
+
+    pub fn parse(&self, data: &str) -> Result<Value, Box<dyn Error>> {
+        Ok(match self {
+            Self::Json => serde_json::from_str(data)?,
+            Self::Toml => toml::from_str(data)?,
+            Self::Yaml => serde_yaml::from_str(data)?,
+        })
+    }
+
+    pub fn serialize(&self, value: &Value) -> Result<String, Box<dyn Error>> {
+        Ok(match self {
+            Self::Json => serde_json::to_string_pretty(value)? + "\n",
+            Self::Toml => toml::to_string_pretty(&toml::Value::try_from(value)?)?,
+            Self::Yaml => serde_yaml::to_string(value)?,
+        })
+    }
+}
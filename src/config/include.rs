@@ -0,0 +1,251 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use super::{format::Format, paths::resolve_path, validation::ConfigIssue, ConfigError};
+
+/// Field holding where a resolved category is defined, e.g.
+/// `taxonomy.json:categories[2]`, as its index in the resolved list differs.
+pub const LOCATION_KEY: &str = "__location";
+
+struct Resolver<'a> {
+    stack: Vec<PathBuf>,
+    sources: Vec<PathBuf>,
+    issues: &'a mut Vec<ConfigIssue>,
+}
+
+/// Replaces every taxonomy reference in the config's categories by the
+/// categories it points to. Referenced files are resolved relative to the
+/// file referencing them. Returns the taxonomy files read.
+pub fn resolve(
+    config: &mut Value,
+    config_path: &Path,
+    issues: &mut Vec<ConfigIssue>,
+) -> Vec<PathBuf> {
+    let mut resolver = Resolver {
+        stack: vec![config_path.canonicalize().unwrap_or_default()],
+        sources: vec![],
+        issues,
+    };
+    let dir = config_path.parent().unwrap_or_else(|| Path::new(""));
+
+    if let Some(categories) = config.get_mut("categories") {
+        *categories = resolver.resolve_categories(categories.take(), dir, "categories");
+    }
+
+    resolver.sources
+}
+
+impl Resolver<'_> {
+    fn push_issue(&mut self, location: String, error: ConfigError) {
+        self.issues.push(ConfigIssue {
+            locations: vec![location],
+            error,
+        });
+    }
+
+    /// Resolves a category list which is either a list, a path to a taxonomy
+    /// file or an include object.
+    fn resolve_categories(&mut self, value: Value, dir: &Path, location: &str) -> Value {
+        match value {
+            Value::String(path) => Value::Array(self.load_taxonomy(&path, dir, location)),
+            Value::Object(reference) => {
+                Value::Array(self.resolve_reference(Value::Object(reference), dir, location))
+            }
+            Value::Array(categories) => {
+                let mut resolved = vec![];
+
+                for (i, category) in categories.into_iter().enumerate() {
+                    let location = format!("{}[{}]", location, i);
+
+                    if category.get("include").is_some() {
+                        resolved.extend(self.resolve_reference(category, dir, &location));
+                    } else {
+                        let mut category = self.resolve_sub_categories(category, dir, &location);
+                        set_location(&mut category, location);
+                        resolved.push(category);
+                    }
+                }
+
+                Value::Array(resolved)
+            }
+            // Left to the deserialization to report
+            value => value,
+        }
+    }
+
+    fn resolve_sub_categories(&mut self, mut category: Value, dir: &Path, location: &str) -> Value {
+        if let Some(sub_categories) = category.get_mut("sub_categories") {
+            *sub_categories = self.resolve_categories(
+                sub_categories.take(),
+                dir,
+                &format!("{}.sub_categories", location),
+            );
+        }
+
+        category
+    }
+
+    /// Loads the categories of an `{ "include": path, "overrides": [...] }`
+    /// object and applies its overrides.
+    fn resolve_reference(&mut self, reference: Value, dir: &Path, location: &str) -> Vec<Value> {
+        let mut categories = match reference.get("include") {
+            Some(Value::String(path)) => {
+                self.load_taxonomy(path, dir, &format!("{}.include", location))
+            }
+            _ => {
+                self.push_issue(
+                    format!("{}.include", location),
+                    ConfigError::Malformed("expected a path to a taxonomy file".to_string()),
+                );
+                return vec![];
+            }
+        };
+
+        match reference.get("overrides") {
+            Some(Value::Array(overrides)) => {
+                for (i, category) in overrides.iter().enumerate() {
+                    let location = format!("{}.overrides[{}]", location, i);
+                    self.apply_override(&mut categories, category, dir, &location);
+                }
+            }
+            Some(_) => self.push_issue(
+                format!("{}.overrides", location),
+                ConfigError::Malformed("expected a list of categories".to_string()),
+            ),
+            None => {}
+        }
+
+        categories
+    }
+
+    /// Replaces the fields of the category with the same name as the override,
+    /// `rename` giving it a new name.
+    fn apply_override(
+        &mut self,
+        categories: &mut [Value],
+        category: &Value,
+        dir: &Path,
+        location: &str,
+    ) {
+        let name = match category.get("name").and_then(Value::as_str) {
+            Some(name) => name,
+            None => {
+                self.push_issue(
+                    format!("{}.name", location),
+                    ConfigError::Malformed("an override must name its category".to_string()),
+                );
+                return;
+            }
+        };
+
+        match find_category(categories, name) {
+            Some(Value::Object(fields)) => {
+                if let Value::Object(overrides) =
+                    self.resolve_sub_categories(category.clone(), dir, location)
+                {
+                    fields.extend(overrides);
+                }
+                // Renamed from the GUI, the override keeps the included name
+                if let Some(new_name) = fields.remove("rename") {
+                    fields.insert("name".to_string(), new_name);
+                }
+                // Issues of the category are most likely due to the override
+                fields.insert(LOCATION_KEY.to_string(), Value::from(location));
+            }
+            _ => self.push_issue(
+                format!("{}.name", location),
+                ConfigError::UnknownCategory(name.to_string()),
+            ),
+        }
+    }
+
+    /// Reads a taxonomy file, which holds either a list of categories or an
+    /// object with a `categories` entry.
+    fn load_taxonomy(&mut self, path: &str, dir: &Path, location: &str) -> Vec<Value> {
+        let path = match resolve_path(path, dir) {
+            Ok(path) => path,
+            Err(error) => {
+                self.push_issue(location.to_string(), error);
+                return vec![];
+            }
+        };
+        let path = path.as_path();
+        let canonical_path = match path.canonicalize() {
+            Ok(canonical_path) => canonical_path,
+            Err(_) => {
+                self.push_issue(
+                    location.to_string(),
+                    ConfigError::MissingPath(path.to_path_buf()),
+                );
+                return vec![];
+            }
+        };
+
+        if self.stack.contains(&canonical_path) {
+            self.push_issue(
+                location.to_string(),
+                ConfigError::IncludeCycle(path.to_path_buf()),
+            );
+            return vec![];
+        }
+
+        let taxonomy = Format::from_path(path)
+            .map_err(|e| e.to_string())
+            .and_then(|format| {
+                std::fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| format.parse(&data).map_err(|e| e.to_string()))
+            });
+        let categories = match taxonomy {
+            Ok(Value::Object(mut taxonomy)) => taxonomy.remove("categories").unwrap_or_default(),
+            Ok(taxonomy) => taxonomy,
+            Err(err) => {
+                self.push_issue(
+                    location.to_string(),
+                    ConfigError::Malformed(format!("{}: {}", path.display(), err)),
+                );
+                return vec![];
+            }
+        };
+
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let location = format!("{}:categories", path.display());
+
+        if !self.sources.contains(&canonical_path) {
+            self.sources.push(canonical_path.clone());
+        }
+        self.stack.push(canonical_path);
+        let categories = self.resolve_categories(categories, dir, &location);
+        self.stack.pop();
+
+        match categories {
+            Value::Array(categories) => categories,
+            _ => {
+                self.push_issue(
+                    location,
+                    ConfigError::Malformed("expected a list of categories".to_string()),
+                );
+                vec![]
+            }
+        }
+    }
+}
+
+fn set_location(category: &mut Value, location: String) {
+    if let Value::Object(fields) = category {
+        fields.insert(LOCATION_KEY.to_string(), Value::String(location));
+    }
+}
+
+fn find_category<'a>(categories: &'a mut [Value], name: &str) -> Option<&'a mut Value> {
+    categories.iter_mut().find_map(|category| {
+        if category.get("name").and_then(Value::as_str) == Some(name) {
+            Some(category)
+        } else {
+            match category.get_mut("sub_categories") {
+                Some(Value::Array(sub_categories)) => find_category(sub_categories, name),
+                _ => None,
+            }
+        }
+    })
+}
@@ -0,0 +1,136 @@
+use egui::Key;
+use log::info;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashSet;
+
+use super::{validation::ConfigIssue, Category, ConfigError};
+
+const LETTERS: [Key; 26] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+
+/// Digit keys, indexed by their digit.
+pub const DIGITS: [Key; 10] = [
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+
+fn key_from_char(c: char) -> Option<Key> {
+    match c.to_ascii_uppercase() {
+        c @ 'A'..='Z' => Some(LETTERS[c as usize - 'A' as usize]),
+        c @ '0'..='9' => Some(DIGITS[c as usize - '0' as usize]),
+        _ => None,
+    }
+}
+
+/// Parses a key binding as written in the config, single characters being
+/// accepted in lower case as well.
+pub fn parse_key(key: &str) -> Option<Key> {
+    let mut chars = key.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => key_from_char(c),
+        _ => Key::deserialize(Value::String(key.to_string())).ok(),
+    }
+}
+
+/// Picks the first free key among the characters of `name`, then among
+/// letters and digits.
+pub fn find_free_key(name: &str, used_keys: &HashSet<Key>) -> Option<Key> {
+    name.chars()
+        .filter_map(key_from_char)
+        .chain(LETTERS)
+        .chain(DIGITS)
+        .find(|key| !used_keys.contains(key))
+}
+
+fn collect_keys(categories: &[Category], used_keys: &mut HashSet<Key>) {
+    categories.iter().for_each(|category| {
+        used_keys.extend(category.key);
+
+        if let Some(sub_categories) = &category.sub_categories {
+            collect_keys(sub_categories, used_keys);
+        }
+    });
+}
+
+/// Assigns a free key to every category without one, in the config order.
+pub fn assign_keys(
+    categories: &mut [Category],
+    reserved_keys: &[Key],
+    issues: &mut Vec<ConfigIssue>,
+) {
+    let mut used_keys = reserved_keys.iter().copied().collect();
+
+    collect_keys(categories, &mut used_keys);
+    assign_keys_inner(categories, "categories", &mut used_keys, issues);
+}
+
+fn assign_keys_inner(
+    categories: &mut [Category],
+    location: &str,
+    used_keys: &mut HashSet<Key>,
+    issues: &mut Vec<ConfigIssue>,
+) {
+    for (i, category) in categories.iter_mut().enumerate() {
+        let location = format!("{}[{}]", location, i);
+
+        if category.key.is_none() {
+            match find_free_key(&category.name, used_keys) {
+                Some(key) => {
+                    info!("Assigned key {:?} to \"{}\"", key, category.name);
+                    used_keys.insert(key);
+                    category.key = Some(key);
+                    category.auto_key = true;
+                }
+                None => issues.push(ConfigIssue {
+                    locations: vec![format!("{}.key", location)],
+                    error: ConfigError::NoFreeKey(category.name.clone()),
+                }),
+            }
+        }
+
+        if let Some(sub_categories) = &mut category.sub_categories {
+            assign_keys_inner(
+                sub_categories,
+                &format!("{}.sub_categories", location),
+                used_keys,
+                issues,
+            );
+        }
+    }
+}
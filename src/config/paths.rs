@@ -0,0 +1,61 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use super::{validation::ConfigIssue, ConfigError};
+
+/// Expands `~` and environment variables in `path`.
+fn expand_path(path: &str) -> Result<PathBuf, ConfigError> {
+    let path = shellexpand::full(path).map_err(|e| ConfigError::UndefinedVariable(e.var_name))?;
+
+    Ok(PathBuf::from(path.as_ref()))
+}
+
+/// Expands `~` and environment variables in `path`, then makes it relative to
+/// `dir` unless it's already absolute.
+pub fn resolve_path(path: &str, dir: &Path) -> Result<PathBuf, ConfigError> {
+    Ok(dir.join(expand_path(path)?))
+}
+
+/// Resolves the input and output paths against the config file's directory.
+pub fn resolve(config: &mut Value, config_path: &Path, issues: &mut Vec<ConfigIssue>) {
+    let dir = config_path
+        .canonicalize()
+        .ok()
+        .and_then(|x| x.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+
+    // `ds` is relative to `root` when both are given
+    let has_root = config.pointer("/input/root").is_some();
+    if has_root {
+        if let Some(Value::String(path)) = config.pointer_mut("/input/ds") {
+            match expand_path(path) {
+                Ok(expanded) => *path = expanded.to_string_lossy().into_owned(),
+                Err(error) => issues.push(ConfigIssue {
+                    locations: vec!["input.ds".to_string()],
+                    error,
+                }),
+            }
+        }
+    }
+    let input = if has_root { "/input/root" } else { "/input/ds" };
+    let pointers = [
+        input,
+        "/output_dir",
+        "/predictions/path",
+        "/queue/scores",
+        "/model/path",
+        "/mode/pairwise/pairs/csv",
+    ];
+
+    for pointer in pointers {
+        if let Some(Value::String(path)) = config.pointer_mut(pointer) {
+            match resolve_path(path, &dir) {
+                Ok(resolved) => *path = resolved.to_string_lossy().into_owned(),
+                Err(error) => issues.push(ConfigIssue {
+                    locations: vec![pointer[1..].replace('/', ".")],
+                    error,
+                }),
+            }
+        }
+    }
+}
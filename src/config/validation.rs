@@ -0,0 +1,665 @@
+use csv::ReaderBuilder;
+use egui::Key;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Debug, Display},
+    fs::OpenOptions,
+    hash::Hash,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    include::LOCATION_KEY, keys, Attribute, AttributeKind, Column, Config, ConfigError, Input,
+    Mode, Model, Notes, Pairs, Predictions, Queue, Sample, DIGITS,
+};
+use crate::category::CategoriesHolder;
+
+pub struct ConfigIssue {
+    pub locations: Vec<String>,
+    pub error: ConfigError,
+}
+
+impl ConfigIssue {
+    fn new(location: String, error: ConfigError) -> Self {
+        Self {
+            locations: vec![location],
+            error,
+        }
+    }
+}
+
+impl Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.locations.join(", "), self.error)
+    }
+}
+
+pub struct ConfigIssues(pub Vec<ConfigIssue>);
+
+impl Error for ConfigIssues {}
+
+impl Debug for ConfigIssues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self)
+    }
+}
+
+impl Display for ConfigIssues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Found {} issue(s) in config:", self.0.len())?;
+        self.0
+            .iter()
+            .try_for_each(|x| writeln!(f, "  - {}", x.to_string().trim_end()))
+    }
+}
+
+struct CategoryEntry {
+    location: String,
+    name: String,
+    key: Option<Key>,
+}
+
+/// Checks the whole config and collects every issue found, each one located
+/// by the JSON path of the offending entry. `issues` holds those found while
+/// loading the config.
+pub fn validate(value: Value, mut issues: Vec<ConfigIssue>) -> Result<Config, ConfigIssues> {
+    // Issues found while loading are already located in their file
+    let nb_loading_issues = issues.len();
+    let mut entries = vec![];
+
+    if let Some(Value::Array(categories)) = value.get("categories") {
+        collect_categories(categories, "categories", &mut entries, &mut issues);
+    }
+    check_name_uniqueness(&entries, &mut issues);
+    check_key_uniqueness(&entries, &mut issues);
+
+    check_reserved_keys(&collect_reserved_keys(&value), &entries, &mut issues);
+    // Pressed with Ctrl, they only clash with each other
+    check_reserved_keys(&collect_ctrl_keys(&value), &[], &mut issues);
+
+    let mut path_issues = vec![];
+    if let Some(Ok(input)) = value.get("input").map(Input::deserialize) {
+        check_input(&input, &mut path_issues);
+    }
+    if let Some(Ok(output_dir)) = value.get("output_dir").map(PathBuf::deserialize) {
+        check_output_dir(&output_dir, &mut path_issues);
+    }
+    if let Some(Ok(predictions)) = value.get("predictions").map(Predictions::deserialize) {
+        if !predictions.path.is_file() {
+            path_issues.push(ConfigIssue::new(
+                "predictions.path".to_string(),
+                ConfigError::MissingPath(predictions.path),
+            ));
+        }
+    }
+    if let Some(Ok(queue)) = value.get("queue").map(Queue::deserialize) {
+        if !queue.scores.is_file() {
+            path_issues.push(ConfigIssue::new(
+                "queue.scores".to_string(),
+                ConfigError::MissingPath(queue.scores),
+            ));
+        }
+    }
+    if let Some(Ok(model)) = value.get("model").map(Model::deserialize) {
+        check_model(&model, &mut path_issues);
+    }
+    if let Some(Ok(Mode::Pairwise {
+        pairs: Pairs::Csv(path),
+    })) = value.get("mode").map(Mode::deserialize)
+    {
+        if !path.is_file() {
+            path_issues.push(ConfigIssue::new(
+                "mode.pairwise.pairs.csv".to_string(),
+                ConfigError::MissingPath(path),
+            ));
+        }
+    }
+    // Paths which couldn't be resolved are already reported
+    path_issues.retain(|x| {
+        !issues
+            .iter()
+            .any(|issue| issue.locations.iter().any(|y| x.locations.contains(y)))
+    });
+    issues.extend(path_issues);
+
+    if let Some(Ok(sample)) = value.get("sample").map(Sample::deserialize) {
+        check_sample(&sample, &mut issues);
+    }
+    check_attributes(&collect_attributes(&value), &mut issues);
+    if let Some(Ok(notes)) = value.get("notes").map(Notes::deserialize) {
+        notes.tags.iter().enumerate().for_each(|(i, tag)| {
+            // Tags are joined with `;` in the export
+            if tag.trim().is_empty() || tag.contains(';') {
+                issues.push(ConfigIssue::new(
+                    format!("notes.tags[{}]", i),
+                    ConfigError::InvalidTag(tag.clone()),
+                ));
+            }
+        });
+    }
+    if let Some(Ok(Mode::Rating { min, max, .. })) = value.get("mode").map(Mode::deserialize) {
+        if min >= max {
+            issues.push(ConfigIssue::new(
+                "mode.rating".to_string(),
+                ConfigError::InvalidRating(format!(
+                    "min must be lower than max, got {} and {}",
+                    min, max
+                )),
+            ));
+        }
+    }
+
+    match serde_path_to_error::deserialize::<_, Config>(&value) {
+        Ok(mut config) if issues.is_empty() => {
+            let reserved_keys = config.reserved_keys();
+            keys::assign_keys(&mut config.categories, &reserved_keys, &mut issues);
+
+            if issues.is_empty() {
+                return Ok(config);
+            }
+        }
+        Ok(_) => {}
+        Err(err) => {
+            let location = err.path().to_string();
+
+            // An invalid key is already reported with more details
+            if !issues.iter().any(|x| x.locations.contains(&location)) {
+                issues.push(ConfigIssue::new(
+                    location,
+                    ConfigError::Malformed(err.into_inner().to_string()),
+                ));
+            }
+        }
+    }
+
+    issues[nb_loading_issues..].iter_mut().for_each(|issue| {
+        issue.locations = issue
+            .locations
+            .iter()
+            .map(|x| source_location(&value, x))
+            .collect();
+    });
+
+    Err(ConfigIssues(issues))
+}
+
+/// Location in the config or taxonomy file of a location in the resolved
+/// config, e.g. `categories[3].key` to `taxonomy.json:categories[1].key`.
+fn source_location(value: &Value, location: &str) -> String {
+    let mut current = value;
+    let mut end = 0;
+    let mut source = None;
+
+    for segment in location.split('.') {
+        let (key, index) = match segment.split_once('[') {
+            Some((key, index)) => (key, index.trim_end_matches(']').parse::<usize>().ok()),
+            None => (segment, None),
+        };
+        let next = current.get(key).and_then(|x| match index {
+            Some(i) => x.get(i),
+            None => Some(x),
+        });
+        current = match next {
+            Some(next) => next,
+            None => break,
+        };
+        end += segment.len();
+
+        if let Some(category_location) = current.get(LOCATION_KEY).and_then(Value::as_str) {
+            source = Some((category_location, end));
+        }
+        end += 1;
+    }
+
+    match source {
+        Some((category_location, end)) => format!("{}{}", category_location, &location[end..]),
+        None => location.to_string(),
+    }
+}
+
+fn collect_categories(
+    categories: &[Value],
+    location: &str,
+    entries: &mut Vec<CategoryEntry>,
+    issues: &mut Vec<ConfigIssue>,
+) {
+    if categories.is_empty() {
+        issues.push(ConfigIssue::new(
+            location.to_string(),
+            ConfigError::EmptyCategories,
+        ));
+    }
+
+    for (i, category) in categories.iter().enumerate() {
+        let location = format!("{}[{}]", location, i);
+
+        let key = category
+            .get("key")
+            .and_then(|key| match Key::deserialize(key) {
+                Ok(key) => Some(key),
+                Err(_) => {
+                    issues.push(ConfigIssue::new(
+                        format!("{}.key", location),
+                        ConfigError::InvalidKey(key.to_string()),
+                    ));
+                    None
+                }
+            });
+
+        if let Some(name) = category.get("name").and_then(Value::as_str) {
+            if category.get("quota").and_then(Value::as_u64) == Some(0) {
+                issues.push(ConfigIssue::new(
+                    format!("{}.quota", location),
+                    ConfigError::InvalidQuota(name.to_string()),
+                ));
+            }
+            if let Some(file) = CategoriesHolder::clashing_export_file(name) {
+                issues.push(ConfigIssue::new(
+                    format!("{}.name", location),
+                    ConfigError::ReservedName((name.to_string(), file.to_string())),
+                ));
+            }
+
+            entries.push(CategoryEntry {
+                location: location.clone(),
+                name: name.to_string(),
+                key,
+            });
+        }
+
+        if let Some(Value::Array(sub_categories)) = category.get("sub_categories") {
+            collect_categories(
+                sub_categories,
+                &format!("{}.sub_categories", location),
+                entries,
+                issues,
+            );
+        }
+    }
+}
+
+/// Keys bound to something else than a category, with their location and
+/// what they do.
+fn collect_reserved_keys(value: &Value) -> Vec<(String, String, Key)> {
+    let mut keys = vec![];
+    let mode = value.get("mode").map(Mode::deserialize);
+
+    // Predictions and suggestions are only shown in single mode
+    let is_single = matches!(mode, None | Some(Ok(Mode::Single)));
+    if let Some(Ok(predictions)) = value
+        .get("predictions")
+        .filter(|_| is_single)
+        .map(Predictions::deserialize)
+    {
+        keys.push((
+            "predictions.accept_key".to_string(),
+            "accepting predictions".to_string(),
+            predictions.accept_key,
+        ));
+    }
+    if let Some(Ok(model)) = value
+        .get("model")
+        .filter(|_| is_single)
+        .map(Model::deserialize)
+    {
+        model.accept_keys.iter().enumerate().for_each(|(i, key)| {
+            keys.push((
+                format!("model.accept_keys[{}]", i),
+                format!("accepting suggestion {}", i + 1),
+                *key,
+            ))
+        });
+    }
+    if let Some(Ok(mode)) = mode {
+        mode.reserved_keys()
+            .into_iter()
+            .for_each(|(key, action)| keys.push(("mode".to_string(), action.to_string(), key)));
+    }
+
+    collect_attributes(value)
+        .into_iter()
+        .for_each(|(i, attribute)| {
+            if let Some(key) = attribute.key {
+                keys.push((
+                    format!("attributes[{}].key", i),
+                    format!("setting {}", attribute.name),
+                    key,
+                ));
+            }
+        });
+
+    keys
+}
+
+/// Keys pressed with Ctrl, as `collect_reserved_keys`.
+fn collect_ctrl_keys(value: &Value) -> Vec<(String, String, Key)> {
+    let mut keys = vec![];
+
+    // Notes are only taken in single and grid modes
+    let mode = value.get("mode").map(Mode::deserialize);
+    if !matches!(mode, None | Some(Ok(Mode::Single | Mode::Grid { .. }))) {
+        return keys;
+    }
+
+    if let Some(Ok(notes)) = value.get("notes").map(Notes::deserialize) {
+        keys.push((
+            "notes.key".to_string(),
+            "opening the note".to_string(),
+            notes.key,
+        ));
+        notes
+            .tags
+            .iter()
+            .zip(&DIGITS[1..])
+            .enumerate()
+            .for_each(|(i, (tag, key))| {
+                keys.push((
+                    format!("notes.tags[{}]", i),
+                    format!("toggling tag {}", tag),
+                    *key,
+                ))
+            });
+    }
+
+    keys
+}
+
+fn check_reserved_keys(
+    reserved_keys: &[(String, String, Key)],
+    entries: &[CategoryEntry],
+    issues: &mut Vec<ConfigIssue>,
+) {
+    let mut checked = vec![];
+
+    for (_, _, key) in reserved_keys {
+        if checked.contains(key) {
+            continue;
+        }
+        checked.push(*key);
+
+        let (locations, names): (Vec<_>, Vec<_>) = entries
+            .iter()
+            .filter(|x| x.key == Some(*key))
+            .map(|x| {
+                (
+                    format!("{}.key", x.location),
+                    format!("category \"{}\"", x.name),
+                )
+            })
+            .chain(
+                reserved_keys
+                    .iter()
+                    .filter(|x| x.2 == *key)
+                    .map(|x| (x.0.clone(), x.1.clone())),
+            )
+            .unzip();
+
+        if locations.len() > 1 {
+            issues.push(ConfigIssue {
+                locations,
+                error: ConfigError::DuplicateBindings((*key, names)),
+            });
+        }
+    }
+}
+
+fn check_model(model: &Model, issues: &mut Vec<ConfigIssue>) {
+    if cfg!(not(feature = "onnx")) {
+        issues.push(ConfigIssue::new(
+            "model".to_string(),
+            ConfigError::MissingFeature("onnx".to_string()),
+        ));
+    }
+    if !model.path.is_file() {
+        issues.push(ConfigIssue::new(
+            "model.path".to_string(),
+            ConfigError::MissingPath(model.path.clone()),
+        ));
+    }
+}
+
+fn check_sample(sample: &Sample, issues: &mut Vec<ConfigIssue>) {
+    let (location, error) = match (sample.size, sample.fraction) {
+        (Some(_), Some(_)) | (None, None) => (
+            "sample",
+            "expected either a `size` or a `fraction`".to_string(),
+        ),
+        (Some(0), _) => ("sample.size", "size must be positive".to_string()),
+        (_, Some(fraction)) if fraction <= 0.0 || fraction > 1.0 => (
+            "sample.fraction",
+            format!("fraction must be in ]0, 1], got {}", fraction),
+        ),
+        _ => return,
+    };
+
+    issues.push(ConfigIssue::new(
+        location.to_string(),
+        ConfigError::InvalidSample(error),
+    ));
+}
+
+/// Attributes which can be deserialized, with their index.
+fn collect_attributes(value: &Value) -> Vec<(usize, Attribute)> {
+    match value.get("attributes") {
+        Some(Value::Array(attributes)) => attributes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| Attribute::deserialize(x).ok().map(|x| (i, x)))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn check_attributes(attributes: &[(usize, Attribute)], issues: &mut Vec<ConfigIssue>) {
+    for (j, (i, attribute)) in attributes.iter().enumerate() {
+        let name = &attribute.name;
+        let error = if ["path", "category"].contains(&name.as_str()) {
+            "the name is already used by a column of the export".to_string()
+        } else if attributes[..j].iter().any(|x| &x.1.name == name) {
+            "the name is used by another attribute".to_string()
+        } else {
+            match &attribute.kind {
+                AttributeKind::Enum { values } if values.is_empty() => {
+                    "expected at least one value".to_string()
+                }
+                AttributeKind::Int {
+                    min: Some(min),
+                    max: Some(max),
+                } if min > max => format!("min must be lower than max, got {} and {}", min, max),
+                AttributeKind::Int { .. } if attribute.key.is_some() => {
+                    "keys only apply to bool and enum attributes".to_string()
+                }
+                _ => continue,
+            }
+        };
+
+        issues.push(ConfigIssue::new(
+            format!("attributes[{}]", i),
+            ConfigError::InvalidAttribute((name.clone(), error)),
+        ));
+    }
+}
+
+/// Groups entries sharing the same value, in order of first appearance.
+fn group_by<T, F>(entries: &[CategoryEntry], f: F) -> Vec<(T, Vec<&CategoryEntry>)>
+where
+    T: Eq + Hash + Clone,
+    F: Fn(&CategoryEntry) -> Option<T>,
+{
+    let mut indices: HashMap<T, usize> = HashMap::new();
+    let mut groups: Vec<(T, Vec<&CategoryEntry>)> = vec![];
+
+    for entry in entries {
+        if let Some(value) = f(entry) {
+            if let Some(&i) = indices.get(&value) {
+                groups[i].1.push(entry);
+            } else {
+                indices.insert(value.clone(), groups.len());
+                groups.push((value, vec![entry]));
+            }
+        }
+    }
+
+    groups
+}
+
+fn check_name_uniqueness(entries: &[CategoryEntry], issues: &mut Vec<ConfigIssue>) {
+    issues.extend(
+        group_by(entries, |x| Some(x.name.clone()))
+            .into_iter()
+            .filter(|x| x.1.len() > 1)
+            .map(|(name, entries)| ConfigIssue {
+                locations: entries
+                    .iter()
+                    .map(|x| format!("{}.name", x.location))
+                    .collect(),
+                error: ConfigError::DuplicateName((name, entries.len())),
+            }),
+    );
+}
+
+fn check_key_uniqueness(entries: &[CategoryEntry], issues: &mut Vec<ConfigIssue>) {
+    issues.extend(
+        group_by(entries, |x| x.key)
+            .into_iter()
+            .filter(|x| x.1.len() > 1)
+            .map(|(key, entries)| ConfigIssue {
+                locations: entries
+                    .iter()
+                    .map(|x| format!("{}.key", x.location))
+                    .collect(),
+                error: ConfigError::DuplicateBindings((
+                    key,
+                    entries.iter().map(|x| x.name.clone()).collect(),
+                )),
+            }),
+    );
+}
+
+fn check_input(input: &Input, issues: &mut Vec<ConfigIssue>) {
+    match input {
+        Input::Dir { root } => {
+            if !root.is_dir() {
+                issues.push(ConfigIssue::new(
+                    "input.root".to_string(),
+                    ConfigError::MissingPath(root.clone()),
+                ));
+            }
+        }
+        Input::Csv {
+            root,
+            has_headers,
+            path_column,
+            caption_column,
+            delimiter,
+            ..
+        } => {
+            if delimiter.is_some_and(|x| !x.is_ascii()) {
+                issues.push(ConfigIssue::new(
+                    "input.delimiter".to_string(),
+                    ConfigError::InvalidColumns(
+                        "the delimiter must be an ASCII character".to_string(),
+                    ),
+                ));
+            }
+            if let Some(root) = root.as_ref().filter(|x| !x.is_dir()) {
+                issues.push(ConfigIssue::new(
+                    "input.root".to_string(),
+                    ConfigError::MissingPath(root.clone()),
+                ));
+                return;
+            }
+            let csv_path = input.path();
+
+            if !csv_path.is_file() {
+                issues.push(ConfigIssue::new(
+                    "input.ds".to_string(),
+                    ConfigError::MissingPath(csv_path.clone()),
+                ));
+            }
+
+            // Columns are looked up in the first record, the headers if any
+            let first_record = ReaderBuilder::new()
+                .has_headers(false)
+                .delimiter(delimiter.map_or(b',', |x| x as u8))
+                .flexible(true)
+                .from_path(&csv_path)
+                .ok()
+                .and_then(|rdr| rdr.into_records().next()?.ok());
+            let headers = first_record.as_ref().filter(|_| *has_headers);
+
+            let columns = [
+                ("input.path_column", Some(path_column)),
+                ("input.caption_column", caption_column.as_ref()),
+            ];
+            let mut indexes = vec![];
+            for (location, column) in columns {
+                let index = match column {
+                    Some(Column::Name(_)) if !has_headers => Err(ConfigError::InvalidColumns(
+                        "column names require `has_headers`".to_string(),
+                    )),
+                    // The file is already reported
+                    Some(Column::Name(_)) if headers.is_none() => continue,
+                    Some(column) => column.index(headers),
+                    None => continue,
+                };
+
+                match (index, &first_record) {
+                    (Ok(i), Some(record)) if i >= record.len() => {
+                        issues.push(ConfigIssue::new(
+                            location.to_string(),
+                            ConfigError::InvalidColumns(format!(
+                                "column {} is out of range, the first row has {} columns",
+                                i,
+                                record.len()
+                            )),
+                        ));
+                    }
+                    (Ok(i), _) => indexes.push(i),
+                    (Err(e), _) => issues.push(ConfigIssue::new(location.to_string(), e)),
+                }
+            }
+
+            if let [path_index, caption_index] = indexes[..] {
+                if path_index == caption_index {
+                    issues.push(ConfigIssue::new(
+                        "input.caption_column".to_string(),
+                        ConfigError::InvalidColumns(
+                            "the caption column holds the paths".to_string(),
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn check_output_dir(output_dir: &Path, issues: &mut Vec<ConfigIssue>) {
+    // The output directory is created at startup, so only its closest
+    // existing ancestor needs to be writable
+    let existing = output_dir
+        .ancestors()
+        .find(|x| !x.as_os_str().is_empty() && x.exists())
+        .unwrap_or_else(|| Path::new("."));
+
+    // Permission bits miss read-only mounts and directories of other users
+    let probe = existing.join(format!(".image_picker_{}", std::process::id()));
+    let writable = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .and_then(|_| std::fs::remove_file(&probe));
+
+    if let Err(e) = writable {
+        issues.push(ConfigIssue::new(
+            "output_dir".to_string(),
+            ConfigError::UnwritableOutputDir((
+                output_dir.to_path_buf(),
+                e.to_string().to_lowercase(),
+            )),
+        ));
+    }
+}
@@ -0,0 +1,100 @@
+use log::info;
+use std::{
+    convert::TryFrom,
+    error::Error,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use super::Config;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Files a config was read from and when they were last modified.
+struct Sources {
+    paths: Vec<PathBuf>,
+    last_modified: Vec<Option<SystemTime>>,
+}
+
+impl Sources {
+    fn new(paths: Vec<PathBuf>) -> Self {
+        Self {
+            last_modified: modification_times(&paths),
+            paths,
+        }
+    }
+}
+
+/// Watches the files a config was read from and reloads it when one of them
+/// changes.
+pub struct ConfigWatcher {
+    rx: Receiver<Result<Config, String>>,
+    sources: Arc<Mutex<Sources>>,
+}
+
+impl ConfigWatcher {
+    pub fn new(ctx: egui::Context, sources: Vec<PathBuf>) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let sources = Arc::new(Mutex::new(Sources::new(sources)));
+        let watched = sources.clone();
+
+        thread::spawn(move || loop {
+            thread::sleep(POLL_INTERVAL);
+
+            let config_path = {
+                let mut sources = watched.lock().unwrap();
+                let modified = modification_times(&sources.paths);
+                if modified == sources.last_modified {
+                    continue;
+                }
+                sources.last_modified = modified;
+                sources.paths[0].clone()
+            };
+
+            info!("Config changed, reloading {}", config_path.display());
+            let config = Config::try_from(config_path.as_path()).map_err(|e| e.to_string());
+
+            if let Ok(config) = &config {
+                // Includes may have changed
+                *watched.lock().unwrap() = Sources::new(config.sources.clone());
+            }
+
+            if tx.send(config).is_err() {
+                break;
+            }
+            ctx.request_repaint();
+        });
+
+        Self { rx, sources }
+    }
+
+    /// Returns the latest reloaded config, if any.
+    pub fn poll(&self) -> Option<Result<Config, String>> {
+        self.rx.try_iter().last()
+    }
+
+    /// Runs a write to the config files which doesn't trigger a reload, as the
+    /// app applies it itself.
+    pub fn write_unwatched(
+        &self,
+        write: impl FnOnce() -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut sources = self.sources.lock().unwrap();
+        let result = write();
+        sources.last_modified = modification_times(&sources.paths);
+
+        result
+    }
+}
+
+fn modification_times(sources: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    sources
+        .iter()
+        .map(|x| x.metadata().and_then(|x| x.modified()).ok())
+        .collect()
+}
@@ -3,36 +3,48 @@ use std::{
     fs::File,
     io::{BufReader, Read},
     path::{Path, PathBuf},
-    sync::mpsc::{self, Receiver},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc,
+    },
     thread::{self, JoinHandle},
     vec::Vec,
 };
 
+use egui::ColorImage;
 use egui_extras::RetainedImage;
 use log::{info, trace, warn};
 
+use crate::inference::{Classifier, Suggestion};
+
 pub struct Image {
     pub source: PathBuf,
     pub buffer: RetainedImage,
+    /// Filled by the data loader when a model is configured
+    pub suggestions: Vec<Suggestion>,
 }
 
 struct DataLoaderThread {
     handle: JoinHandle<()>,
-    rx: Receiver<Option<Image>>,
+    rx: Receiver<Result<Image, PathBuf>>,
 }
 
 pub struct DataLoader {
     paths: Vec<PathBuf>,
     threads: Vec<DataLoaderThread>,
     buffer: VecDeque<Image>,
+    unreadable: Vec<PathBuf>,
+    classifier: Option<Arc<Classifier>>,
 }
 
 impl DataLoader {
-    pub fn new(capacity: usize, paths: Vec<PathBuf>) -> Self {
+    pub fn new(capacity: usize, paths: Vec<PathBuf>, classifier: Option<Arc<Classifier>>) -> Self {
         let mut preloader = Self {
             paths,
+            classifier,
             threads: Vec::with_capacity(capacity),
             buffer: VecDeque::with_capacity(capacity),
+            unreadable: vec![],
         };
         preloader.prefetch_images();
         preloader.await_fetched_images(Some(1));
@@ -43,19 +55,43 @@ impl DataLoader {
     pub fn read_current(&mut self) -> Option<Image> {
         self.prefetch_images();
         self.collect_readed_images();
-        if self.buffer.is_empty() {
+        // Unreadable images leave the buffer empty while images remain
+        while self.buffer.is_empty() && !self.threads.is_empty() {
             self.await_fetched_images(Some(1));
+            self.prefetch_images();
         }
 
         self.buffer.pop_back()
     }
 
+    /// Queues images to read after the ones already queued.
+    pub fn append(&mut self, paths: Vec<PathBuf>) {
+        self.paths.splice(0..0, paths);
+    }
+
+    /// Reorders the images which aren't prefetched yet, the last ones are read
+    /// first.
+    pub fn reorder<F: FnOnce(&mut Vec<PathBuf>)>(&mut self, f: F) {
+        f(&mut self.paths);
+    }
+
+    pub fn get_nb_unreadable(&self) -> usize {
+        self.unreadable.len()
+    }
+
+    pub fn is_unreadable(&self, path: &Path) -> bool {
+        self.unreadable.iter().any(|x| x == path)
+    }
+
     fn prefetch_image(&mut self) -> bool {
         if let Some(path) = self.paths.pop() {
             let (tx, rx) = mpsc::channel();
+            let classifier = self.classifier.clone();
 
             let handle = thread::spawn(move || {
-                tx.send(read_image(path)).unwrap();
+                let source = path.clone();
+                let image = read_image(path, classifier.as_deref());
+                tx.send(image.ok_or(source)).unwrap();
             });
 
             self.threads.push(DataLoaderThread { handle, rx });
@@ -97,12 +133,11 @@ impl DataLoader {
                 }
 
                 match x.rx.recv() {
-                    Ok(content) => {
-                        if let Some(image) = content {
-                            trace!("Adding {} to buffer", image.source.display());
-                            self.buffer.push_front(image);
-                        }
+                    Ok(Ok(image)) => {
+                        trace!("Adding {} to buffer", image.source.display());
+                        self.buffer.push_front(image);
                     }
+                    Ok(Err(path)) => self.unreadable.push(path),
                     Err(err) => warn!("{}", err),
                 };
             },
@@ -127,18 +162,21 @@ impl DataLoader {
                 x.handle.join().unwrap();
                 *n = n.saturating_sub(1);
 
-                if let Ok(content) =  x.rx.recv() {
-                    if let Some(image)= content  {
+                match x.rx.recv() {
+                    Ok(Ok(image)) => {
                         trace!("Adding {} to buffer", image.source.display());
                         self.buffer.push_front(image);
                     }
+                    Ok(Err(path)) => self.unreadable.push(path),
+                    Err(err) => warn!("{}", err),
                 }
             },
         );
     }
 }
 
-fn read_image(path: PathBuf) -> Option<Image> {
+/// Reads an image, suggesting its categories with the classifier if any.
+pub fn read_image(path: PathBuf, classifier: Option<&Classifier>) -> Option<Image> {
     let print_error = |path: &Path, err: String| {
         warn!(
             "Couldn't read {} because {}",
@@ -160,16 +198,27 @@ fn read_image(path: PathBuf) -> Option<Image> {
         print_error(&path, err.to_string());
     }
 
-    match RetainedImage::from_image_bytes(path.to_string_lossy(), &buffer) {
-        Ok(buffer) => {
+    // Decoded once for both the display and the classifier
+    match image::load_from_memory(&buffer) {
+        Ok(image) => {
+            let suggestions = classifier
+                .map(|classifier| classifier.suggest(&path, &image))
+                .unwrap_or_default();
+            let size = [image.width() as usize, image.height() as usize];
+            let pixels = ColorImage::from_rgba_unmultiplied(
+                size,
+                image.to_rgba8().as_flat_samples().as_slice(),
+            );
             trace!("Finnish reading {}", path.display());
+
             Some(Image {
+                buffer: RetainedImage::from_color_image(path.to_string_lossy(), pixels),
                 source: path,
-                buffer,
+                suggestions,
             })
         }
         Err(err) => {
-            print_error(&path, err);
+            print_error(&path, err.to_string());
             None
         }
     }
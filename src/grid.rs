@@ -0,0 +1,158 @@
+use egui::{Color32, ImageButton, Key, Stroke};
+
+use crate::{data_loader::Image, shortcuts::shortcuts_enabled};
+
+/// Several images labeled together. Images which aren't selected when a
+/// category is assigned stay in the grid.
+pub struct GridView {
+    size: usize,
+    images: Vec<Image>,
+    selected: Vec<bool>,
+    cursor: usize,
+    /// Start of the range selected with shift-click
+    anchor: usize,
+}
+
+impl GridView {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size: size.max(1),
+            images: vec![],
+            selected: vec![],
+            cursor: 0,
+            anchor: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.images.len() >= self.size
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn push(&mut self, image: Image) {
+        self.images.push(image);
+        self.selected.push(false);
+    }
+
+    pub fn get_nb_selected(&self) -> usize {
+        self.selected.iter().filter(|x| **x).count()
+    }
+
+    /// Removes the selected images, or the one under the cursor if none is.
+    pub fn take_selected(&mut self) -> Vec<Image> {
+        if self.is_empty() {
+            return vec![];
+        }
+        if self.get_nb_selected() == 0 {
+            self.selected[self.cursor] = true;
+        }
+
+        let (selected, kept): (Vec<_>, Vec<_>) = self
+            .images
+            .drain(..)
+            .zip(self.selected.drain(..))
+            .partition(|x| x.1);
+        self.images = kept.into_iter().map(|x| x.0).collect();
+        self.selected = vec![false; self.images.len()];
+        self.cursor = self.cursor.min(self.images.len().saturating_sub(1));
+        self.anchor = self.cursor;
+
+        selected.into_iter().map(|x| x.0).collect()
+    }
+
+    fn columns(&self) -> usize {
+        (self.size as f32).sqrt().ceil() as usize
+    }
+
+    fn move_cursor(&mut self, ctx: &egui::Context) {
+        let last = self.images.len().saturating_sub(1);
+        let columns = self.columns();
+        let input = ctx.input();
+
+        if input.key_pressed(Key::ArrowLeft) {
+            self.cursor = self.cursor.saturating_sub(1);
+        }
+        if input.key_pressed(Key::ArrowRight) {
+            self.cursor = (self.cursor + 1).min(last);
+        }
+        if input.key_pressed(Key::ArrowUp) {
+            self.cursor = self.cursor.saturating_sub(columns);
+        }
+        if input.key_pressed(Key::ArrowDown) && self.cursor + columns <= last {
+            self.cursor += columns;
+        }
+        if input.key_pressed(Key::Space) {
+            self.selected[self.cursor] = !self.selected[self.cursor];
+            self.anchor = self.cursor;
+        }
+    }
+
+    fn click(&mut self, index: usize, shift: bool) {
+        if shift {
+            let (start, end) = (self.anchor.min(index), self.anchor.max(index));
+            self.selected[start..=end]
+                .iter_mut()
+                .for_each(|x| *x = true);
+        } else {
+            self.selected[index] = !self.selected[index];
+            self.anchor = index;
+        }
+        self.cursor = index;
+    }
+
+    pub fn update(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        if self.is_empty() {
+            return;
+        }
+        if shortcuts_enabled(ctx) {
+            self.move_cursor(ctx);
+        }
+
+        let columns = self.columns();
+        let rows = self.size.div_ceil(columns);
+        let spacing = ui.spacing().item_spacing;
+        let cell = ((ui.available_width() - spacing.x * columns as f32) / columns as f32)
+            .min((ui.available_height() - spacing.y * rows as f32) / rows as f32)
+            .max(16.0);
+
+        let mut clicked = None;
+        egui::Grid::new("Image grid").show(ui, |ui| {
+            self.images.iter().enumerate().for_each(|(i, image)| {
+                let size = image.buffer.size_vec2();
+                let scale = (cell / size.x).min(cell / size.y);
+                let response = ui
+                    .add(
+                        ImageButton::new(image.buffer.texture_id(ctx), size * scale)
+                            .selected(self.selected[i]),
+                    )
+                    .on_hover_text(image.source.display().to_string());
+
+                if i == self.cursor {
+                    ui.painter().rect_stroke(
+                        response.rect.expand(2.0),
+                        0.0,
+                        Stroke::new(2.0, Color32::YELLOW),
+                    );
+                }
+                if response.clicked() {
+                    clicked = Some(i);
+                }
+                if (i + 1) % columns == 0 {
+                    ui.end_row();
+                }
+            });
+        });
+
+        if let Some(i) = clicked {
+            let shift = ctx.input().modifiers.shift;
+            self.click(i, shift);
+        }
+    }
+}
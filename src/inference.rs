@@ -0,0 +1,118 @@
+use std::{error::Error, path::Path};
+
+use egui::Key;
+use image::DynamicImage;
+#[cfg(feature = "onnx")]
+use log::info;
+use log::warn;
+
+use crate::config::Model;
+
+pub struct Suggestion {
+    pub category: String,
+    pub score: f32,
+}
+
+/// Suggests categories for images with a local ONNX model, on the CPU.
+pub struct Classifier {
+    config: Model,
+    #[cfg(feature = "onnx")]
+    model: tract_onnx::prelude::TypedRunnableModel<tract_onnx::prelude::TypedModel>,
+}
+
+impl Classifier {
+    #[cfg(feature = "onnx")]
+    pub fn load(config: Model) -> Result<Self, Box<dyn Error>> {
+        use tract_onnx::prelude::*;
+
+        let [width, height] = config.input_size;
+        let model = tract_onnx::onnx()
+            .model_for_path(&config.path)?
+            .with_input_fact(0, f32::fact([1, 3, height, width]).into())?
+            .into_optimized()?
+            .into_runnable()?;
+        info!("Loaded model from {}", config.path.display());
+
+        Ok(Self { config, model })
+    }
+
+    #[cfg(not(feature = "onnx"))]
+    pub fn load(_config: Model) -> Result<Self, Box<dyn Error>> {
+        Err(Box::new(crate::config::ConfigError::MissingFeature(
+            "onnx".to_string(),
+        )))
+    }
+
+    pub fn config(&self) -> &Model {
+        &self.config
+    }
+
+    pub fn accept_keys(&self) -> &[Key] {
+        &self.config.accept_keys
+    }
+
+    /// Category suggested for each output of the model.
+    pub fn categories(&self) -> impl Iterator<Item = &String> {
+        self.config
+            .labels
+            .iter()
+            .map(|label| self.config.categories.get(label).unwrap_or(label))
+    }
+
+    /// Most likely categories of an image, one per accept key.
+    pub fn suggest(&self, path: &Path, image: &DynamicImage) -> Vec<Suggestion> {
+        let mut scores = match self.run(image) {
+            Ok(scores) => scores,
+            Err(e) => {
+                warn!("Couldn't classify {}: {}", path.display(), e);
+                return vec![];
+            }
+        };
+
+        if self.config.softmax {
+            let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            scores.iter_mut().for_each(|x| *x = (*x - max).exp());
+            let sum = scores.iter().sum::<f32>();
+            scores.iter_mut().for_each(|x| *x /= sum);
+        }
+
+        let mut suggestions = self
+            .categories()
+            .zip(scores)
+            .map(|(category, score)| Suggestion {
+                category: category.clone(),
+                score,
+            })
+            .collect::<Vec<_>>();
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+        suggestions.truncate(self.config.accept_keys.len());
+
+        suggestions
+    }
+
+    #[cfg(feature = "onnx")]
+    fn run(&self, image: &DynamicImage) -> Result<Vec<f32>, Box<dyn Error>> {
+        use image::imageops::FilterType;
+        use tract_onnx::prelude::*;
+
+        let [width, height] = self.config.input_size;
+        let image = image
+            .resize_exact(width as u32, height as u32, FilterType::Triangle)
+            .to_rgb8();
+        let (mean, std) = (self.config.mean, self.config.std);
+
+        let input: Tensor =
+            tract_ndarray::Array4::from_shape_fn((1, 3, height, width), |(_, c, y, x)| {
+                (image[(x as u32, y as u32)][c] as f32 / 255.0 - mean[c]) / std[c]
+            })
+            .into();
+        let outputs = self.model.run(tvec!(input.into()))?;
+
+        Ok(outputs[0].to_array_view::<f32>()?.iter().copied().collect())
+    }
+
+    #[cfg(not(feature = "onnx"))]
+    fn run(&self, _image: &DynamicImage) -> Result<Vec<f32>, Box<dyn Error>> {
+        Ok(vec![])
+    }
+}
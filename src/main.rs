@@ -1,22 +1,54 @@
 use eframe::egui;
 use env_logger::Builder;
+use log::info;
 
 mod app;
+mod attributes;
+mod captions;
 mod category;
 mod config;
 mod data_loader;
+mod grid;
+mod inference;
+mod metadata;
+mod notes;
+mod pairwise;
+mod pre_labels;
 mod progression;
+mod rating;
+mod regions;
+mod review;
+mod sampling;
+mod shortcuts;
+mod statistics;
+mod summary;
+mod uncertainty;
 
-use std::{convert::TryFrom, error::Error, path::Path};
+use std::{convert::TryFrom, env, error::Error, path::Path};
 
 use app::ImagePicker;
 use config::Config;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let config = Config::try_from(Path::new("./config.json"))?;
-
     Builder::new().filter_level(log::LevelFilter::Info).init();
 
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    if args.first().map(String::as_str) == Some("validate") {
+        let path = args.get(1).map_or(Config::default_path(), Path::new);
+
+        Config::try_from(path)?;
+        println!("{} is valid", path.display());
+        return Ok(());
+    }
+
+    let path = Config::default_path();
+    let config = Config::try_from(path)?;
+    info!(
+        "Loaded config from {}",
+        path.canonicalize().unwrap_or_default().display()
+    );
+
     let options = eframe::NativeOptions {
         initial_window_size: Some(egui::vec2(700.0, 700.0)),
         ..Default::default()
@@ -0,0 +1,56 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Columns of a CSV input besides the paths, written after the path in the
+/// category CSVs.
+#[derive(Default)]
+pub struct Metadata {
+    /// Names of the columns, when the input has headers
+    headers: Option<Vec<String>>,
+    nb_columns: usize,
+    rows: HashMap<PathBuf, Vec<String>>,
+}
+
+impl Metadata {
+    pub const PATH_HEADER: &'static str = "path";
+
+    pub fn new(headers: Option<Vec<String>>) -> Self {
+        Self {
+            nb_columns: headers.as_ref().map_or(0, Vec::len),
+            headers,
+            rows: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, path: PathBuf, values: Vec<String>) {
+        self.nb_columns = self.nb_columns.max(values.len());
+        self.rows.insert(path, values);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nb_columns == 0
+    }
+
+    /// Header of the category CSVs, `None` when the input has no headers.
+    pub fn header(&self) -> Option<Vec<&str>> {
+        let headers = self.headers.as_ref().filter(|_| !self.is_empty())?;
+
+        Some(
+            [Self::PATH_HEADER]
+                .into_iter()
+                .chain(headers.iter().map(String::as_str))
+                .collect(),
+        )
+    }
+
+    /// Values of an image, empty for the ones which aren't in the input.
+    pub fn row(&self, path: &Path) -> Vec<&str> {
+        let values = self.rows.get(path).map(Vec::as_slice).unwrap_or_default();
+
+        (0..self.nb_columns)
+            .map(|i| values.get(i).map(String::as_str).unwrap_or_default())
+            .collect()
+    }
+}
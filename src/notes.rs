@@ -0,0 +1,214 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use csv::{ReaderBuilder, WriterBuilder};
+use egui::TextEdit;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Notes, DIGITS};
+
+#[derive(Serialize, Deserialize)]
+struct NoteRow {
+    path: PathBuf,
+    category: String,
+    note: String,
+    /// Joined with `;`
+    tags: String,
+}
+
+struct Note {
+    category: String,
+    text: String,
+    tags: Vec<String>,
+}
+
+/// Note and tags of the current image, and the ones written so far.
+pub struct NoteEditor {
+    config: Notes,
+    text: String,
+    selected_tags: Vec<bool>,
+    is_open: bool,
+    /// Focuses the text field once it's shown
+    focus: bool,
+    notes: BTreeMap<PathBuf, Note>,
+}
+
+impl NoteEditor {
+    pub const NOTES_FILE: &'static str = "notes.csv";
+
+    /// Loads the notes written in previous sessions.
+    pub fn load(config: Notes, output_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let notes_path = output_dir.join(Self::NOTES_FILE);
+        let notes = if notes_path.exists() {
+            ReaderBuilder::new()
+                .from_path(notes_path)?
+                .deserialize()
+                .map(|row| {
+                    row.map(|row: NoteRow| {
+                        let note = Note {
+                            category: row.category,
+                            text: row.note,
+                            tags: row
+                                .tags
+                                .split(';')
+                                .filter(|x| !x.is_empty())
+                                .map(String::from)
+                                .collect(),
+                        };
+                        (row.path, note)
+                    })
+                })
+                .collect::<Result<BTreeMap<_, _>, _>>()?
+        } else {
+            BTreeMap::new()
+        };
+        info!("Readed {} notes", notes.len());
+
+        Ok(Self {
+            selected_tags: vec![false; config.tags.len()],
+            config,
+            text: String::new(),
+            is_open: false,
+            focus: false,
+            notes,
+        })
+    }
+
+    pub fn config(&self) -> &Notes {
+        &self.config
+    }
+
+    /// Stores the note and tags of an image, if any.
+    pub fn record(&mut self, path: &Path, category: &str) {
+        let tags = self
+            .config
+            .tags
+            .iter()
+            .zip(&self.selected_tags)
+            .filter(|x| *x.1)
+            .map(|x| x.0.clone())
+            .collect::<Vec<_>>();
+
+        if !self.text.trim().is_empty() || !tags.is_empty() {
+            self.notes.insert(
+                path.to_path_buf(),
+                Note {
+                    category: category.to_string(),
+                    text: self.text.trim().to_string(),
+                    tags,
+                },
+            );
+        }
+    }
+
+    /// Renames the category of the images noted so far.
+    pub fn rename_category(&mut self, name: &str, new_name: &str) {
+        self.notes
+            .values_mut()
+            .filter(|x| x.category == name)
+            .for_each(|x| x.category = new_name.to_string());
+    }
+
+    /// Empties the note for the next image.
+    pub fn reset(&mut self) {
+        self.text.clear();
+        self.selected_tags.iter_mut().for_each(|x| *x = false);
+        self.is_open = false;
+    }
+
+    /// Note and tags of each image, as searched in review.
+    pub fn texts(&self) -> HashMap<PathBuf, String> {
+        self.notes
+            .iter()
+            .map(|(path, note)| {
+                let text = if note.tags.is_empty() {
+                    note.text.clone()
+                } else {
+                    format!("{} [{}]", note.text, note.tags.join(", "))
+                };
+                (path.clone(), text.trim().to_string())
+            })
+            .collect()
+    }
+
+    fn handle_keys(&mut self, ctx: &egui::Context) {
+        let input = ctx.input();
+        // Keys without Ctrl are left to categories
+        if !input.modifiers.command {
+            return;
+        }
+
+        if input.key_pressed(self.config.key) {
+            self.is_open = !self.is_open;
+            self.focus = self.is_open;
+        }
+        // Ctrl+1 toggles the first tag
+        DIGITS[1..]
+            .iter()
+            .zip(self.selected_tags.iter_mut())
+            .for_each(|(key, selected)| {
+                if input.key_pressed(*key) {
+                    *selected = !*selected;
+                }
+            });
+    }
+
+    pub fn update(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        self.handle_keys(ctx);
+
+        ui.horizontal_wrapped(|ui| {
+            let label = format!("Note [Ctrl+{:?}]", self.config.key);
+            if ui.selectable_label(self.is_open, label).clicked() {
+                self.is_open = !self.is_open;
+                self.focus = self.is_open;
+            }
+
+            self.config
+                .tags
+                .iter()
+                .zip(self.selected_tags.iter_mut())
+                .enumerate()
+                .for_each(|(i, (tag, selected))| {
+                    let label = if i < DIGITS.len() - 1 {
+                        format!("{} [Ctrl+{}]", tag, i + 1)
+                    } else {
+                        tag.clone()
+                    };
+                    if ui.selectable_label(*selected, label).clicked() {
+                        *selected = !*selected;
+                    }
+                });
+        });
+
+        if self.is_open {
+            let response = ui.add(
+                TextEdit::singleline(&mut self.text)
+                    .hint_text("Why this category?")
+                    .desired_width(f32::INFINITY),
+            );
+            if self.focus {
+                response.request_focus();
+                self.focus = false;
+            }
+        }
+    }
+
+    pub fn export(&self, output_dir: &Path) -> Result<(), Box<dyn Error>> {
+        let mut wdr = WriterBuilder::new().from_path(output_dir.join(Self::NOTES_FILE))?;
+
+        self.notes.iter().try_for_each(|(path, note)| {
+            wdr.serialize(NoteRow {
+                path: path.clone(),
+                category: note.category.clone(),
+                note: note.text.clone(),
+                tags: note.tags.join(";"),
+            })
+        })?;
+
+        Ok(())
+    }
+}
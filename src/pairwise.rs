@@ -0,0 +1,386 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use csv::{ReaderBuilder, WriterBuilder};
+use egui::{Key, RichText};
+use log::{info, warn};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Pairs,
+    data_loader::{DataLoader, Image},
+    shortcuts::shortcuts_enabled,
+};
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Choice {
+    Left,
+    Right,
+    Tie,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Comparison {
+    left: PathBuf,
+    right: PathBuf,
+    choice: Choice,
+}
+
+type Pair = (PathBuf, PathBuf);
+type PairIter = Box<dyn Iterator<Item = Pair>>;
+
+/// Two images at a time, the labeler chooses the better one.
+pub struct PairwiseView {
+    /// Pairs not queued in the data loader yet, drawn as they're needed
+    upcoming: PairIter,
+    /// Pairs whose images are queued in the data loader
+    pairs: VecDeque<Pair>,
+    nb_pairs: usize,
+    /// Images read ahead of their pair
+    pending: Vec<Image>,
+    /// Images of skipped pairs, dropped once read
+    discarded: Vec<PathBuf>,
+    current: Option<(Image, Image)>,
+    comparisons: Vec<Comparison>,
+}
+
+impl PairwiseView {
+    pub const COMPARISONS_FILE: &'static str = "comparisons.csv";
+    const NB_QUEUED_PAIRS: usize = 32;
+
+    /// Pairs already compared in previous sessions are skipped.
+    pub fn new(
+        pairs: &Pairs,
+        paths: &[PathBuf],
+        output_dir: &Path,
+    ) -> Result<Self, Box<dyn Error>> {
+        let comparisons_path = output_dir.join(Self::COMPARISONS_FILE);
+        let comparisons = if comparisons_path.exists() {
+            ReaderBuilder::new()
+                .from_path(comparisons_path)?
+                .deserialize()
+                .collect::<Result<Vec<Comparison>, _>>()?
+        } else {
+            vec![]
+        };
+        let compared = comparisons
+            .iter()
+            .map(|x| (x.left.clone(), x.right.clone()))
+            .collect::<HashSet<_>>();
+
+        let (nb_pairs, upcoming) = make_pairs(pairs, paths, compared)?;
+        info!(
+            "{} pairs left to compare, {} already compared",
+            nb_pairs,
+            comparisons.len()
+        );
+
+        let mut view = Self {
+            upcoming,
+            pairs: VecDeque::new(),
+            nb_pairs,
+            pending: vec![],
+            discarded: vec![],
+            current: None,
+            comparisons,
+        };
+        view.queue_pairs();
+
+        Ok(view)
+    }
+
+    /// Draws the next pairs, returning their paths in the order the data
+    /// loader reads them.
+    fn queue_pairs(&mut self) -> Vec<PathBuf> {
+        let nb_missing = Self::NB_QUEUED_PAIRS.saturating_sub(self.pairs.len());
+        let pairs = self.upcoming.by_ref().take(nb_missing).collect::<Vec<_>>();
+        self.pairs.extend(pairs.iter().cloned());
+
+        pairs
+            .into_iter()
+            .rev()
+            .flat_map(|(left, right)| [right, left])
+            .collect()
+    }
+
+    /// Paths to read first, in the order the data loader reads them.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.pairs
+            .iter()
+            .rev()
+            .flat_map(|(left, right)| [right.clone(), left.clone()])
+            .collect()
+    }
+
+    pub fn get_nb_pairs(&self) -> usize {
+        self.nb_pairs
+    }
+
+    pub fn get_nb_compared(&self) -> usize {
+        self.comparisons.len()
+    }
+
+    fn take_pending(&mut self, path: &Path) -> Option<Image> {
+        let i = self.pending.iter().position(|x| x.source == path)?;
+
+        Some(self.pending.swap_remove(i))
+    }
+
+    /// Reads the next pair whose images are both readable. Returns the left
+    /// image path, `None` once every pair is compared.
+    pub fn next(&mut self, dataloader: &mut DataLoader) -> Option<PathBuf> {
+        if let Some((left, _)) = &self.current {
+            return Some(left.source.clone());
+        }
+
+        loop {
+            let paths = self.queue_pairs();
+            dataloader.append(paths);
+            let (left, right) = self.pairs.front().cloned()?;
+
+            let is_pending = |view: &Self, path: &Path| {
+                view.pending.iter().filter(|x| x.source == path).count()
+                    >= if left == right { 2 } else { 1 }
+            };
+
+            if is_pending(self, &left) && is_pending(self, &right) {
+                self.pairs.pop_front();
+                let left_image = self.take_pending(&left)?;
+                let right_image = self.take_pending(&right)?;
+                self.current = Some((left_image, right_image));
+
+                return Some(left);
+            }
+
+            let image = if dataloader.is_unreadable(&left) || dataloader.is_unreadable(&right) {
+                None
+            } else {
+                dataloader.read_current()
+            };
+
+            match image {
+                Some(image) => match self.discarded.iter().position(|x| *x == image.source) {
+                    Some(i) => {
+                        self.discarded.swap_remove(i);
+                    }
+                    None => self.pending.push(image),
+                },
+                None => {
+                    warn!(
+                        "Skipping the pair {} and {}, an image is unreadable",
+                        left.display(),
+                        right.display()
+                    );
+                    self.pairs.pop_front();
+                    // The readable image may not be read yet
+                    for path in [left, right] {
+                        if self.take_pending(&path).is_none() && !dataloader.is_unreadable(&path) {
+                            self.discarded.push(path);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn choose(&mut self, choice: Choice) {
+        if let Some((left, right)) = self.current.take() {
+            self.comparisons.push(Comparison {
+                left: left.source,
+                right: right.source,
+                choice,
+            });
+        }
+    }
+
+    /// Returns `true` once the current pair is compared.
+    pub fn update(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) -> bool {
+        let (left, right) = match &self.current {
+            Some(current) => current,
+            None => return false,
+        };
+        let mut choice = None;
+
+        ui.horizontal(|ui| {
+            if ui.button("⬅ Left is better").clicked() {
+                choice = Some(Choice::Left);
+            }
+            if ui.button("⬇ Tie").clicked() {
+                choice = Some(Choice::Tie);
+            }
+            if ui.button("Right is better ➡").clicked() {
+                choice = Some(Choice::Right);
+            }
+        });
+
+        ui.columns(2, |columns| {
+            [left, right]
+                .into_iter()
+                .zip(columns.iter_mut())
+                .for_each(|(image, ui)| {
+                    ui.vertical_centered(|ui| {
+                        ui.label(RichText::new(image.source.display().to_string()).small());
+
+                        let buffer = &image.buffer;
+                        let factors = ui.available_size() / buffer.size_vec2();
+                        buffer.show_scaled(ui, factors.min_elem());
+                    });
+                });
+        });
+
+        if shortcuts_enabled(ctx) {
+            let input = ctx.input();
+
+            if input.key_pressed(Key::ArrowLeft) {
+                choice = Some(Choice::Left);
+            } else if input.key_pressed(Key::ArrowRight) {
+                choice = Some(Choice::Right);
+            } else if input.key_pressed(Key::ArrowDown) {
+                choice = Some(Choice::Tie);
+            }
+        }
+
+        match choice {
+            Some(choice) => {
+                self.choose(choice);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn export(&self, output_dir: &Path) -> Result<(), Box<dyn Error>> {
+        let mut wdr = WriterBuilder::new().from_path(output_dir.join(Self::COMPARISONS_FILE))?;
+
+        self.comparisons
+            .iter()
+            .try_for_each(|comparison| wdr.serialize(comparison))?;
+
+        Ok(())
+    }
+}
+
+/// Pairs left to compare, and their number.
+fn make_pairs(
+    pairs: &Pairs,
+    paths: &[PathBuf],
+    compared: HashSet<Pair>,
+) -> Result<(usize, PairIter), Box<dyn Error>> {
+    // The same seed must draw the same pairs
+    let mut paths = paths.to_vec();
+    paths.sort();
+    paths.dedup();
+
+    let pairs = match pairs {
+        // Every pair would hardly fit in memory for large inputs
+        Pairs::All => {
+            let nb_pairs = paths.len() * paths.len().saturating_sub(1) / 2;
+            let nb_compared = compared
+                .iter()
+                .filter(|(left, right)| {
+                    left < right
+                        && paths.binary_search(left).is_ok()
+                        && paths.binary_search(right).is_ok()
+                })
+                .count();
+            let pairs = AllPairs {
+                paths,
+                left: 0,
+                right: 1,
+            }
+            .filter(move |x| !compared.contains(x));
+
+            return Ok((nb_pairs - nb_compared, Box::new(pairs)));
+        }
+        Pairs::Random { count, seed } => {
+            let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+            let mut rng = StdRng::seed_from_u64(seed);
+            let nb_pairs = paths.len() * paths.len().saturating_sub(1) / 2;
+            info!("Drawing {} random pairs with seed {}", count, seed);
+
+            // Drawing pairs one by one would hardly find the last free ones
+            let mut pairs = if count * 2 >= nb_pairs {
+                let mut pairs = AllPairs {
+                    paths: paths.clone(),
+                    left: 0,
+                    right: 1,
+                }
+                .collect::<Vec<_>>();
+                pairs.shuffle(&mut rng);
+                pairs.truncate(*count);
+                pairs
+            } else {
+                let mut drawn = HashSet::new();
+                while drawn.len() < *count {
+                    let i = rng.gen_range(0..paths.len());
+                    let j = rng.gen_range(0..paths.len());
+                    if i != j {
+                        drawn.insert((i.min(j), i.max(j)));
+                    }
+                }
+
+                let mut drawn = drawn.into_iter().collect::<Vec<_>>();
+                drawn.sort();
+                drawn.shuffle(&mut rng);
+                drawn
+                    .into_iter()
+                    .map(|(i, j)| (paths[i].clone(), paths[j].clone()))
+                    .collect()
+            };
+
+            // Avoids always showing the same image on the same side
+            pairs.iter_mut().for_each(|pair| {
+                if rng.gen_bool(0.5) {
+                    *pair = (pair.1.clone(), pair.0.clone());
+                }
+            });
+
+            pairs
+        }
+        Pairs::Csv(path) => {
+            info!("Loading pairs from CSV file: {}", path.display());
+
+            ReaderBuilder::new()
+                .has_headers(false)
+                .from_path(path)?
+                .deserialize()
+                .collect::<Result<Vec<Pair>, _>>()?
+        }
+    };
+    let pairs = pairs
+        .into_iter()
+        .filter(|x| !compared.contains(x))
+        .collect::<Vec<_>>();
+
+    Ok((pairs.len(), Box::new(pairs.into_iter())))
+}
+
+/// Every pair of sorted paths, in order.
+struct AllPairs {
+    paths: Vec<PathBuf>,
+    left: usize,
+    right: usize,
+}
+
+impl Iterator for AllPairs {
+    type Item = Pair;
+
+    fn next(&mut self) -> Option<Pair> {
+        if self.right >= self.paths.len() {
+            self.left += 1;
+            self.right = self.left + 1;
+        }
+        let pair = (
+            self.paths.get(self.left)?.clone(),
+            self.paths.get(self.right)?.clone(),
+        );
+        self.right += 1;
+
+        Some(pair)
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use csv::{ReaderBuilder, WriterBuilder};
+use egui::Key;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Predictions;
+
+pub struct Prediction {
+    pub category: String,
+    pub score: f32,
+}
+
+/// How a prediction was reviewed.
+#[derive(Serialize, Deserialize)]
+struct Outcome {
+    path: PathBuf,
+    predicted: String,
+    category: String,
+    accepted: bool,
+}
+
+/// Model predictions and whether the labeler accepted or corrected them.
+pub struct PreLabels {
+    config: Predictions,
+    /// Relative paths of the predictions and images are resolved against it
+    input_root: PathBuf,
+    predictions: HashMap<PathBuf, Prediction>,
+    outcomes: BTreeMap<PathBuf, Outcome>,
+}
+
+impl PreLabels {
+    pub const OUTCOMES_FILE: &'static str = "prediction_outcomes.csv";
+
+    pub fn load(
+        config: Predictions,
+        input_root: &Path,
+        output_dir: &Path,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .from_path(&config.path)?;
+        let predictions = rdr
+            .deserialize()
+            .collect::<Result<Vec<(PathBuf, String, f32)>, _>>()?
+            .into_iter()
+            .map(|(path, category, score)| {
+                (
+                    resolve_path(&path, input_root),
+                    Prediction { category, score },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        info!(
+            "Readed {} predictions from {}",
+            predictions.len(),
+            config.path.display()
+        );
+
+        let outcomes_path = output_dir.join(Self::OUTCOMES_FILE);
+        let outcomes = if outcomes_path.exists() {
+            ReaderBuilder::new()
+                .from_path(outcomes_path)?
+                .deserialize()
+                .map(|x| x.map(|x: Outcome| (x.path.clone(), x)))
+                .collect::<Result<_, _>>()?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            config,
+            input_root: input_root.to_path_buf(),
+            predictions,
+            outcomes,
+        })
+    }
+
+    pub fn config(&self) -> &Predictions {
+        &self.config
+    }
+
+    pub fn accept_key(&self) -> Key {
+        self.config.accept_key
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&Prediction> {
+        self.predictions.get(&resolve_path(path, &self.input_root))
+    }
+
+    /// Warns when no image has a prediction, their paths most likely differ.
+    pub fn check_paths(&self, paths: &[PathBuf]) {
+        let nb_matches = paths.iter().filter(|x| self.get(x).is_some()).count();
+
+        if nb_matches == 0 && !self.predictions.is_empty() && !paths.is_empty() {
+            warn!(
+                "None of the {} predictions matches an image, their paths must be \
+                 absolute or relative to {}",
+                self.predictions.len(),
+                self.input_root.display()
+            );
+        } else {
+            info!("{} images to label have a prediction", nb_matches);
+        }
+    }
+
+    /// Records the category given to an image which has a prediction.
+    pub fn record(&mut self, path: &Path, category: &str) {
+        if let Some(predicted) = self.get(path).map(|x| x.category.clone()) {
+            self.outcomes.insert(
+                path.to_path_buf(),
+                Outcome {
+                    path: path.to_path_buf(),
+                    accepted: predicted == category,
+                    predicted,
+                    category: category.to_string(),
+                },
+            );
+        }
+    }
+
+    /// Renames a category in the predictions and their outcomes, so that
+    /// accepting a prediction still selects it.
+    pub fn rename_category(&mut self, name: &str, new_name: &str) {
+        let predictions = self.predictions.values_mut().map(|x| &mut x.category);
+        let outcomes = self
+            .outcomes
+            .values_mut()
+            .flat_map(|x| [&mut x.predicted, &mut x.category]);
+
+        predictions
+            .chain(outcomes)
+            .filter(|x| *x == name)
+            .for_each(|x| *x = new_name.to_string());
+    }
+
+    pub fn export(&self, output_dir: &Path) -> Result<(), Box<dyn Error>> {
+        let mut wdr = WriterBuilder::new().from_path(output_dir.join(Self::OUTCOMES_FILE))?;
+
+        self.outcomes
+            .values()
+            .try_for_each(|outcome| wdr.serialize(outcome))?;
+
+        Ok(())
+    }
+}
+
+fn resolve_path(path: &Path, input_root: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        input_root.join(path)
+    }
+}
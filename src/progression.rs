@@ -1,22 +1,100 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use egui::{ProgressBar, RichText};
+
+pub struct DirectoryProgression {
+    pub path: PathBuf,
+    pub done: usize,
+    pub total: usize,
+}
+
 pub struct Progression {
     current: usize,
     goal: usize,
+    /// Images categorized during previous sessions
+    nb_done_before: usize,
+    directories: Vec<DirectoryProgression>,
+    directory_indices: HashMap<PathBuf, usize>,
 }
 
 impl Progression {
-    pub fn new(goal: usize) -> Self {
-        Self { current: 0, goal }
+    pub fn new(goal: usize, nb_done_before: usize, directories: Vec<DirectoryProgression>) -> Self {
+        let directory_indices = directories
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (x.path.clone(), i))
+            .collect();
+
+        Self {
+            current: 0,
+            goal,
+            nb_done_before,
+            directories,
+            directory_indices,
+        }
     }
 
-    pub fn step(&mut self) {
+    pub fn step(&mut self, path: &Path) {
         self.current = self.current.saturating_add(1);
+
+        if let Some(&i) = path.parent().and_then(|x| self.directory_indices.get(x)) {
+            self.directories[i].done += 1;
+        }
     }
 
     pub fn compute_progress(&self) -> f32 {
         self.current as f32 / self.goal as f32
     }
 
+    pub fn compute_overall_progress(&self) -> f32 {
+        self.get_nb_overall_done() as f32 / self.get_nb_overall_step() as f32
+    }
+
     pub fn get_nb_remaining_step(&self) -> usize {
         self.goal - self.current
     }
+
+    pub fn get_nb_step(&self) -> usize {
+        self.goal
+    }
+
+    pub fn get_nb_done(&self) -> usize {
+        self.current
+    }
+
+    pub fn get_nb_overall_step(&self) -> usize {
+        self.nb_done_before + self.goal
+    }
+
+    pub fn get_nb_overall_done(&self) -> usize {
+        self.nb_done_before + self.current
+    }
+
+    /// Progression of each input sub directory, empty for CSV inputs.
+    pub fn get_directories(&self) -> &[DirectoryProgression] {
+        &self.directories
+    }
+
+    pub fn update(&self, ui: &mut egui::Ui) {
+        ui.label(RichText::new(format!(
+            "{} left",
+            self.get_nb_remaining_step()
+        )));
+
+        ui.add(ProgressBar::new(self.compute_progress()).text(format!(
+            "Session: {}/{}",
+            self.get_nb_done(),
+            self.get_nb_step()
+        )));
+        ui.add(
+            ProgressBar::new(self.compute_overall_progress()).text(format!(
+                "Overall: {}/{}",
+                self.get_nb_overall_done(),
+                self.get_nb_overall_step()
+            )),
+        );
+    }
 }
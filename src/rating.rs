@@ -0,0 +1,184 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use csv::{ReaderBuilder, WriterBuilder};
+use egui::{Key, RichText};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::DIGITS, data_loader::Image, shortcuts::shortcuts_enabled};
+
+#[derive(Serialize, Deserialize)]
+struct Rating {
+    path: PathBuf,
+    score: f32,
+}
+
+#[derive(Serialize)]
+struct HistogramBin {
+    score: f32,
+    count: usize,
+}
+
+/// Scores given to images, on a scale from `min` to `max`.
+pub struct Ratings {
+    min: u32,
+    max: u32,
+    half_steps: bool,
+    ratings: BTreeMap<PathBuf, f32>,
+    /// Digits typed of a score which may have more
+    pending: String,
+}
+
+impl Ratings {
+    pub const RATINGS_FILE: &'static str = "ratings.csv";
+    pub const HISTOGRAM_FILE: &'static str = "ratings_histogram.csv";
+
+    /// Loads the ratings given in previous sessions.
+    pub fn load(
+        min: u32,
+        max: u32,
+        half_steps: bool,
+        output_dir: &Path,
+    ) -> Result<Self, Box<dyn Error>> {
+        let ratings_path = output_dir.join(Self::RATINGS_FILE);
+        let ratings = if ratings_path.exists() {
+            ReaderBuilder::new()
+                .from_path(ratings_path)?
+                .deserialize()
+                .map(|x| x.map(|x: Rating| (x.path, x.score)))
+                .collect::<Result<BTreeMap<_, _>, _>>()?
+        } else {
+            BTreeMap::new()
+        };
+        info!("Readed {} ratings", ratings.len());
+
+        Ok(Self {
+            min,
+            max,
+            half_steps,
+            ratings,
+            pending: String::new(),
+        })
+    }
+
+    /// Images already rated.
+    pub fn get_paths(&self) -> HashSet<PathBuf> {
+        self.ratings.keys().cloned().collect()
+    }
+
+    /// Every score of the scale, in half points.
+    fn steps(&self) -> impl Iterator<Item = u32> {
+        let step = if self.half_steps { 1 } else { 2 };
+
+        (self.min * 2..=self.max * 2).step_by(step)
+    }
+
+    /// Score of a digit key press, `None` while more digits may follow.
+    fn type_digit(&mut self, digit: usize, shift: bool) -> Option<f32> {
+        let typed = format!("{}{}", self.pending, digit);
+        let value = typed.parse::<u32>().ok()?;
+        let is_valid = (self.min..=self.max).contains(&value);
+        let may_continue = value != 0 && value * 10 <= self.max;
+        self.pending.clear();
+
+        if shift && self.half_steps && is_valid && value < self.max {
+            Some(value as f32 + 0.5)
+        } else if is_valid && !may_continue {
+            Some(value as f32)
+        } else {
+            if may_continue {
+                self.pending = typed;
+            }
+            None
+        }
+    }
+
+    fn read_keys(&mut self, ctx: &egui::Context) -> Option<f32> {
+        let input = ctx.input();
+
+        if input.key_pressed(Key::Enter) {
+            let value = self.pending.parse::<u32>().ok();
+            self.pending.clear();
+
+            return value
+                .filter(|x| (self.min..=self.max).contains(x))
+                .map(|x| x as f32);
+        }
+
+        let shift = input.modifiers.shift;
+        let digit = DIGITS.iter().position(|x| input.key_pressed(*x))?;
+        drop(input);
+
+        self.type_digit(digit, shift)
+    }
+
+    /// Returns `true` once the image is rated.
+    pub fn update(&mut self, ctx: &egui::Context, ui: &mut egui::Ui, image: &Image) -> bool {
+        let mut score = None;
+
+        if shortcuts_enabled(ctx) {
+            score = self.read_keys(ctx);
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Score:");
+            self.steps().for_each(|x| {
+                if ui.button((x as f32 / 2.0).to_string()).clicked() {
+                    score = Some(x as f32 / 2.0);
+                }
+            });
+            if !self.pending.is_empty() {
+                ui.label(RichText::new(format!("{}_", self.pending)).strong());
+            }
+        });
+
+        ui.with_layout(
+            egui::Layout::top_down_justified(egui::Align::Center),
+            |ui| {
+                let buffer = &image.buffer;
+                let factors = ui.available_size() / buffer.size_vec2();
+
+                buffer.show_scaled(ui, factors.min_elem());
+            },
+        );
+
+        match score {
+            Some(score) => {
+                self.ratings.insert(image.source.clone(), score);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Writes the ratings and the number of images per score.
+    pub fn export(&self, output_dir: &Path) -> Result<(), Box<dyn Error>> {
+        let mut wdr = WriterBuilder::new().from_path(output_dir.join(Self::RATINGS_FILE))?;
+        self.ratings.iter().try_for_each(|(path, score)| {
+            wdr.serialize(Rating {
+                path: path.clone(),
+                score: *score,
+            })
+        })?;
+
+        // Scores outside of the scale come from a previous config
+        let mut histogram = self.steps().map(|x| (x, 0)).collect::<BTreeMap<_, _>>();
+        self.ratings.values().for_each(|x| {
+            *histogram.entry((x * 2.0).round() as u32).or_default() += 1;
+        });
+
+        let mut wdr = WriterBuilder::new().from_path(output_dir.join(Self::HISTOGRAM_FILE))?;
+        histogram.into_iter().try_for_each(|(score, count)| {
+            wdr.serialize(HistogramBin {
+                score: score as f32 / 2.0,
+                count,
+            })
+        })?;
+
+        Ok(())
+    }
+}
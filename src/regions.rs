@@ -0,0 +1,569 @@
+mod coco;
+mod geometry;
+mod yolo;
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use egui::{Align2, Color32, FontId, Key, Pos2, Rect, Sense, Stroke};
+use log::{info, warn};
+
+use crate::{config::Shape, data_loader::Image, shortcuts::shortcuts_enabled};
+use geometry::{corners, Geometry};
+
+#[derive(Clone)]
+pub struct Region {
+    pub category: Option<String>,
+    pub geometry: Geometry,
+}
+
+pub struct AnnotatedImage {
+    pub path: PathBuf,
+    pub width: usize,
+    pub height: usize,
+    pub regions: Vec<Region>,
+}
+
+impl AnnotatedImage {
+    fn labeled_regions(&self) -> impl Iterator<Item = (&String, &Geometry)> {
+        self.regions
+            .iter()
+            .filter_map(|x| x.category.as_ref().map(|category| (category, &x.geometry)))
+    }
+}
+
+/// Shape being drawn or edited.
+enum Drawing {
+    Box(Pos2),
+    Polygon(Vec<Pos2>),
+    Keypoints(Vec<Option<Pos2>>),
+    MoveVertex {
+        region: usize,
+        vertex: usize,
+        anchor: Option<Pos2>,
+    },
+}
+
+/// Regions drawn on images, exported to COCO and YOLO.
+pub struct Regions {
+    images: BTreeMap<PathBuf, AnnotatedImage>,
+    /// Regions of the image being labeled
+    current: Vec<Region>,
+    drawing: Option<Drawing>,
+    /// Category given to new regions, with its shape
+    active: Option<(String, Shape)>,
+    /// Regions of the image being labeled before each edit
+    history: Vec<Vec<Region>>,
+}
+
+impl Regions {
+    const COCO_FILE: &'static str = "regions.coco.json";
+    const YOLO_DIR: &'static str = "yolo";
+    /// Distance in screen pixels under which a vertex is grabbed or snapped to
+    const SNAP_RADIUS: f32 = 8.0;
+
+    /// Loads the regions drawn in previous sessions.
+    pub fn load(output_dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let coco_path = output_dir.join(Self::COCO_FILE);
+        let images = if coco_path.exists() {
+            coco::read(&coco_path)?
+        } else {
+            vec![]
+        };
+        info!("Readed regions of {} images", images.len());
+
+        Ok(Self {
+            images: images.into_iter().map(|x| (x.path.clone(), x)).collect(),
+            current: vec![],
+            drawing: None,
+            active: None,
+            history: vec![],
+        })
+    }
+
+    /// Images already labeled.
+    pub fn get_paths(&self) -> HashSet<PathBuf> {
+        self.images.keys().cloned().collect()
+    }
+
+    /// Labels the last box drawn if it has no category yet, and the next
+    /// regions. `shapes` are the shapes of the categories.
+    pub fn label(&mut self, category: String, shapes: &[(String, Shape)]) {
+        let shape = shape_of(&category, shapes);
+
+        if let Some(region) = self.current.last_mut().filter(|x| x.category.is_none()) {
+            // Boxes can't become keypoints
+            match (&shape, &region.geometry) {
+                (Shape::Box, _) => region.category = Some(category.clone()),
+                (Shape::Polygon, Geometry::Box(rect)) => {
+                    region.category = Some(category.clone());
+                    region.geometry = Geometry::Polygon(corners(rect).to_vec());
+                }
+                _ => {}
+            }
+        }
+        // The points drawn so far wouldn't fit another shape
+        if self.active.as_ref().map(|x| &x.1) != Some(&shape) {
+            self.cancel_drawing();
+        }
+        self.active = Some((category, shape));
+    }
+
+    /// Stores the regions of the image being labeled.
+    pub fn finish(&mut self, image: &Image) {
+        let [width, height] = image.buffer.size();
+        let nb_unlabeled = self.current.iter().filter(|x| x.category.is_none()).count();
+        if nb_unlabeled > 0 {
+            warn!(
+                "Dropping {} regions without category of {}",
+                nb_unlabeled,
+                image.source.display()
+            );
+        }
+
+        self.drawing = None;
+        self.history.clear();
+        self.images.insert(
+            image.source.clone(),
+            AnnotatedImage {
+                path: image.source.clone(),
+                width,
+                height,
+                regions: std::mem::take(&mut self.current)
+                    .into_iter()
+                    .filter(|x| x.category.is_some())
+                    .collect(),
+            },
+        );
+    }
+
+    fn active_shape(&self) -> Shape {
+        self.active
+            .as_ref()
+            .map(|x| x.1.clone())
+            .unwrap_or_default()
+    }
+
+    fn save_history(&mut self) {
+        self.history.push(self.current.clone());
+    }
+
+    fn push_region(&mut self, geometry: Geometry) {
+        self.save_history();
+        self.current.push(Region {
+            category: self.active.as_ref().map(|x| x.0.clone()),
+            geometry,
+        });
+    }
+
+    fn remove_last(&mut self) {
+        if !self.current.is_empty() {
+            self.save_history();
+            self.current.pop();
+        }
+    }
+
+    fn cancel_drawing(&mut self) {
+        if !matches!(self.drawing, Some(Drawing::MoveVertex { .. })) {
+            self.drawing = None;
+        }
+    }
+
+    /// Removes the last point drawn, or reverts the last edit.
+    fn undo(&mut self) {
+        match &mut self.drawing {
+            Some(Drawing::Polygon(points)) if !points.is_empty() => {
+                points.pop();
+            }
+            Some(Drawing::Keypoints(points)) if !points.is_empty() => {
+                points.pop();
+            }
+            _ => {
+                self.drawing = None;
+                if let Some(regions) = self.history.pop() {
+                    self.current = regions;
+                }
+            }
+        }
+    }
+
+    /// Region and vertex closest to `pos`, within `radius`.
+    fn nearest_vertex(&self, pos: Pos2, radius: f32) -> Option<(usize, usize)> {
+        self.current
+            .iter()
+            .enumerate()
+            .flat_map(|(i, region)| {
+                region
+                    .geometry
+                    .vertices()
+                    .into_iter()
+                    .map(move |(j, vertex)| (i, j, vertex.distance(pos)))
+            })
+            .filter(|x| x.2 <= radius)
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|x| (x.0, x.1))
+    }
+
+    /// Existing vertex close to `pos`, so that regions can share borders.
+    fn snap(&self, pos: Pos2, radius: f32) -> Pos2 {
+        self.nearest_vertex(pos, radius)
+            .and_then(|(i, j)| {
+                let vertices = self.current[i].geometry.vertices();
+                vertices.into_iter().find(|x| x.0 == j).map(|x| x.1)
+            })
+            .unwrap_or(pos)
+    }
+
+    fn start_drag(&mut self, pos: Pos2, radius: f32) {
+        match self.drawing {
+            Some(Drawing::Polygon(_) | Drawing::Keypoints(_)) => {}
+            _ => {
+                if let Some((region, vertex)) = self.nearest_vertex(pos, radius) {
+                    let anchor = match &self.current[region].geometry {
+                        Geometry::Box(rect) => Some(corners(rect)[(vertex + 2) % 4]),
+                        _ => None,
+                    };
+                    self.save_history();
+                    self.drawing = Some(Drawing::MoveVertex {
+                        region,
+                        vertex,
+                        anchor,
+                    });
+                } else if self.active_shape() == Shape::Box {
+                    self.drawing = Some(Drawing::Box(pos));
+                }
+            }
+        }
+    }
+
+    fn drag(&mut self, pos: Pos2) {
+        if let Some(Drawing::MoveVertex {
+            region,
+            vertex,
+            anchor,
+        }) = self.drawing
+        {
+            if let Some(region) = self.current.get_mut(region) {
+                region.geometry.move_vertex(vertex, pos, anchor);
+            }
+        }
+    }
+
+    fn release(&mut self, pos: Pos2) {
+        match self.drawing {
+            Some(Drawing::Box(start)) => {
+                self.drawing = None;
+
+                // Clicks must not create boxes
+                let rect = Rect::from_two_pos(start, pos);
+                if rect.width() >= 2.0 && rect.height() >= 2.0 {
+                    self.push_region(Geometry::Box(rect));
+                }
+            }
+            Some(Drawing::MoveVertex { .. }) => self.drawing = None,
+            _ => {}
+        }
+    }
+
+    fn click(&mut self, pos: Pos2, radius: f32) {
+        match self.active_shape() {
+            Shape::Box => {}
+            Shape::Polygon => {
+                let pos = self.snap(pos, radius);
+                let mut points = match self.drawing.take() {
+                    Some(Drawing::Polygon(points)) => points,
+                    _ => vec![],
+                };
+
+                // Clicking the first vertex closes the polygon
+                if points.len() >= 3 && points[0].distance(pos) <= radius {
+                    self.push_region(Geometry::Polygon(points));
+                } else {
+                    points.push(pos);
+                    self.drawing = Some(Drawing::Polygon(points));
+                }
+            }
+            Shape::Keypoints(names) => self.add_keypoint(Some(pos), names.len()),
+        }
+    }
+
+    /// Adds the next keypoint, `None` when it isn't visible.
+    fn add_keypoint(&mut self, pos: Option<Pos2>, nb_keypoints: usize) {
+        let mut points = match self.drawing.take() {
+            Some(Drawing::Keypoints(points)) => points,
+            _ => vec![],
+        };
+
+        points.push(pos);
+        if points.len() >= nb_keypoints {
+            // Such a region has no bounding box to export
+            if points.iter().all(Option::is_none) {
+                warn!("Region dropped as none of its keypoints is visible");
+                return;
+            }
+            self.push_region(Geometry::Keypoints(points));
+        } else {
+            self.drawing = Some(Drawing::Keypoints(points));
+        }
+    }
+
+    /// What clicking or dragging on the image does.
+    fn hint(&self) -> String {
+        let (category, shape) = match &self.active {
+            Some(active) => active,
+            None => return "drag to draw boxes, then press a category key".to_string(),
+        };
+
+        match shape {
+            Shape::Box => format!("drag to draw a box of {}", category),
+            Shape::Polygon => format!(
+                "click to add vertices of a {}, click the first one to close it",
+                category
+            ),
+            Shape::Keypoints(names) => {
+                let next = match &self.drawing {
+                    Some(Drawing::Keypoints(points)) => points.len(),
+                    _ => 0,
+                };
+                format!(
+                    "click the {} keypoint of {}",
+                    names.get(next).map(String::as_str).unwrap_or("next"),
+                    category
+                )
+            }
+        }
+    }
+
+    /// Returns `true` once the image is done. `shapes` are the shapes of the
+    /// categories.
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut egui::Ui,
+        image: &Image,
+        shapes: &[(String, Shape)],
+    ) -> bool {
+        let mut done = false;
+
+        if shortcuts_enabled(ctx) {
+            let input = ctx.input();
+            let (undo, remove, cancel) = (
+                input.modifiers.command && input.key_pressed(Key::Z),
+                input.key_pressed(Key::Backspace),
+                input.key_pressed(Key::Escape),
+            );
+            done = input.key_pressed(Key::Enter);
+            drop(input);
+
+            if undo {
+                self.undo();
+            }
+            if remove {
+                self.remove_last();
+            }
+            if cancel {
+                self.cancel_drawing();
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(format!("{} regions, {}", self.current.len(), self.hint()));
+            if let Shape::Keypoints(names) = self.active_shape() {
+                if ui.button("Skip keypoint").clicked() {
+                    self.add_keypoint(None, names.len());
+                }
+            }
+            if ui.button("Undo").clicked() {
+                self.undo();
+            }
+            if ui.button("Remove last").clicked() {
+                self.remove_last();
+            }
+            if ui.button("Next image").clicked() {
+                done = true;
+            }
+        });
+
+        ui.with_layout(
+            egui::Layout::top_down_justified(egui::Align::Center),
+            |ui| {
+                let size = image.buffer.size_vec2();
+                let scale = (ui.available_size() / size).min_elem();
+                let response = ui.add(
+                    egui::Image::new(image.buffer.texture_id(ctx), size * scale)
+                        .sense(Sense::click_and_drag()),
+                );
+
+                let origin = response.rect.min;
+                let radius = Self::SNAP_RADIUS / scale;
+                let to_image = |pos: Pos2| {
+                    let pos = ((pos - origin) / scale).to_pos2();
+                    pos.clamp(Pos2::ZERO, size.to_pos2())
+                };
+                let to_screen = |pos: Pos2| origin + pos.to_vec2() * scale;
+
+                if let Some(pos) = response.interact_pointer_pos().map(to_image) {
+                    if response.drag_started() {
+                        self.start_drag(pos, radius);
+                    }
+                    if response.dragged() {
+                        self.drag(pos);
+                    }
+                    if response.drag_released() {
+                        self.release(pos);
+                    }
+                    if response.clicked() {
+                        self.click(pos, radius);
+                    }
+                }
+
+                let painter = ui.painter_at(response.rect);
+                let font = FontId::proportional(14.0);
+                for region in &self.current {
+                    let color = match region.category {
+                        Some(_) => Color32::GREEN,
+                        None => Color32::YELLOW,
+                    };
+                    let stroke = Stroke::new(2.0, color);
+
+                    match &region.geometry {
+                        Geometry::Box(rect) => painter.rect_stroke(
+                            Rect::from_two_pos(to_screen(rect.min), to_screen(rect.max)),
+                            0.0,
+                            stroke,
+                        ),
+                        Geometry::Polygon(points) => {
+                            painter.add(egui::Shape::closed_line(
+                                points.iter().copied().map(to_screen).collect(),
+                                stroke,
+                            ));
+                        }
+                        Geometry::Keypoints(points) => {
+                            let shape = region.category.as_ref().map(|x| shape_of(x, shapes));
+                            let names = match shape {
+                                Some(Shape::Keypoints(names)) => names,
+                                _ => vec![],
+                            };
+                            points.iter().enumerate().for_each(|(i, point)| {
+                                if let Some(point) = point {
+                                    painter.circle_filled(to_screen(*point), 3.0, color);
+                                    if let Some(name) = names.get(i) {
+                                        painter.text(
+                                            to_screen(*point),
+                                            Align2::LEFT_TOP,
+                                            name,
+                                            FontId::proportional(11.0),
+                                            color,
+                                        );
+                                    }
+                                }
+                            });
+                        }
+                    }
+                    if let (Some(category), Some(rect)) =
+                        (&region.category, region.geometry.bounding_rect())
+                    {
+                        painter.text(
+                            to_screen(rect.left_top()),
+                            Align2::LEFT_BOTTOM,
+                            category,
+                            font.clone(),
+                            color,
+                        );
+                    }
+                }
+
+                let stroke = Stroke::new(1.0, Color32::WHITE);
+                let hover = response.hover_pos();
+                match &self.drawing {
+                    Some(Drawing::Box(start)) => {
+                        if let Some(end) = hover {
+                            painter.rect_stroke(
+                                Rect::from_two_pos(to_screen(*start), end),
+                                0.0,
+                                stroke,
+                            );
+                        }
+                    }
+                    Some(Drawing::Polygon(points)) => {
+                        let mut points = points.iter().copied().map(to_screen).collect::<Vec<_>>();
+                        points.extend(hover);
+                        painter.add(egui::Shape::line(points, stroke));
+                    }
+                    Some(Drawing::Keypoints(points)) => {
+                        points.iter().flatten().for_each(|point| {
+                            painter.circle_filled(to_screen(*point), 3.0, Color32::WHITE);
+                        });
+                    }
+                    _ => {}
+                }
+            },
+        );
+
+        done
+    }
+
+    /// Renames the category of the regions drawn so far.
+    pub fn rename_category(&mut self, name: &str, new_name: &str) {
+        let images = self.images.values_mut().flat_map(|x| x.regions.iter_mut());
+        let current = self.current.iter_mut();
+        let history = self.history.iter_mut().flatten();
+
+        images
+            .chain(current)
+            .chain(history)
+            .filter_map(|x| x.category.as_mut())
+            .chain(self.active.as_mut().map(|x| &mut x.0))
+            .filter(|x| *x == name)
+            .for_each(|x| *x = new_name.to_string());
+    }
+
+    /// `categories` are the categories with their shape.
+    pub fn export(
+        &self,
+        output_dir: &Path,
+        input_root: &Path,
+        categories: &[(String, Shape)],
+    ) -> Result<(), Box<dyn Error>> {
+        // Categories removed from the config keep their regions
+        let mut categories = categories.to_vec();
+        self.images
+            .values()
+            .flat_map(|x| x.labeled_regions())
+            .for_each(|(category, geometry)| {
+                if !categories.iter().any(|x| &x.0 == category) {
+                    let shape = match geometry {
+                        Geometry::Box(_) => Shape::Box,
+                        Geometry::Polygon(_) => Shape::Polygon,
+                        Geometry::Keypoints(points) => {
+                            Shape::Keypoints((1..=points.len()).map(|i| i.to_string()).collect())
+                        }
+                    };
+                    categories.push((category.clone(), shape));
+                }
+            });
+
+        coco::write(
+            &output_dir.join(Self::COCO_FILE),
+            self.images.values(),
+            &categories,
+        )?;
+        yolo::write(
+            &output_dir.join(Self::YOLO_DIR),
+            input_root,
+            self.images.values(),
+            &categories,
+        )
+    }
+}
+
+fn shape_of(category: &str, shapes: &[(String, Shape)]) -> Shape {
+    shapes
+        .iter()
+        .find(|x| x.0 == category)
+        .map(|x| x.1.clone())
+        .unwrap_or_default()
+}
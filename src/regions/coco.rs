@@ -0,0 +1,202 @@
+use std::{collections::BTreeMap, error::Error, path::Path};
+
+use egui::{Pos2, Rect};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::{geometry::Geometry, AnnotatedImage, Region};
+use crate::config::Shape;
+
+#[derive(Default, Serialize, Deserialize)]
+struct Coco {
+    images: Vec<CocoImage>,
+    annotations: Vec<CocoAnnotation>,
+    categories: Vec<CocoCategory>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CocoImage {
+    id: usize,
+    file_name: String,
+    width: usize,
+    height: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CocoAnnotation {
+    id: usize,
+    image_id: usize,
+    category_id: usize,
+    /// `[x, y, width, height]` in pixels
+    bbox: [f32; 4],
+    area: f32,
+    iscrowd: u8,
+    /// Polygons as `[x1, y1, x2, y2, ...]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    segmentation: Option<Vec<Vec<f32>>>,
+    /// `[x1, y1, v1, ...]` where `v` is 0 for keypoints which aren't visible
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keypoints: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    num_keypoints: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CocoCategory {
+    id: usize,
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keypoints: Option<Vec<String>>,
+}
+
+impl CocoAnnotation {
+    fn geometry(&self) -> Geometry {
+        if let Some(keypoints) = &self.keypoints {
+            Geometry::Keypoints(
+                keypoints
+                    .chunks(3)
+                    .map(|x| match x {
+                        [x, y, v] if *v > 0.0 => Some(Pos2::new(*x, *y)),
+                        _ => None,
+                    })
+                    .collect(),
+            )
+        } else if let Some(polygon) = self.segmentation.as_ref().and_then(|x| x.first()) {
+            Geometry::Polygon(
+                polygon
+                    .chunks(2)
+                    .filter_map(|x| match x {
+                        [x, y] => Some(Pos2::new(*x, *y)),
+                        _ => None,
+                    })
+                    .collect(),
+            )
+        } else {
+            let [x, y, width, height] = self.bbox;
+            Geometry::Box(Rect::from_min_size(
+                Pos2::new(x, y),
+                egui::vec2(width, height),
+            ))
+        }
+    }
+}
+
+pub fn read(path: &Path) -> Result<Vec<AnnotatedImage>, Box<dyn Error>> {
+    let coco: Coco = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let categories = coco
+        .categories
+        .into_iter()
+        .map(|x| (x.id, x.name))
+        .collect::<BTreeMap<_, _>>();
+    let mut images = coco
+        .images
+        .into_iter()
+        .map(|x| {
+            let image = AnnotatedImage {
+                path: x.file_name.into(),
+                width: x.width,
+                height: x.height,
+                regions: vec![],
+            };
+            (x.id, image)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    for annotation in coco.annotations {
+        if let (Some(image), Some(category)) = (
+            images.get_mut(&annotation.image_id),
+            categories.get(&annotation.category_id),
+        ) {
+            image.regions.push(Region {
+                category: Some(category.clone()),
+                geometry: annotation.geometry(),
+            });
+        }
+    }
+
+    Ok(images.into_values().collect())
+}
+
+pub fn write<'a>(
+    path: &Path,
+    images: impl Iterator<Item = &'a AnnotatedImage>,
+    categories: &[(String, Shape)],
+) -> Result<(), Box<dyn Error>> {
+    let mut coco = Coco {
+        categories: categories
+            .iter()
+            .enumerate()
+            .map(|(i, (name, shape))| CocoCategory {
+                id: i + 1,
+                name: name.clone(),
+                keypoints: match shape {
+                    Shape::Keypoints(names) => Some(names.clone()),
+                    _ => None,
+                },
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    for (i, image) in images.enumerate() {
+        coco.images.push(CocoImage {
+            id: i + 1,
+            file_name: image.path.to_string_lossy().into_owned(),
+            width: image.width,
+            height: image.height,
+        });
+
+        for (category, geometry) in image.labeled_regions() {
+            let (rect, area) = match (geometry.bounding_rect(), geometry.area()) {
+                (Some(rect), Some(area)) => (rect, area),
+                _ => {
+                    warn!(
+                        "Region of {} skipped as it has no visible point",
+                        image.path.display()
+                    );
+                    continue;
+                }
+            };
+            let mut annotation = CocoAnnotation {
+                id: coco.annotations.len() + 1,
+                image_id: i + 1,
+                category_id: categories
+                    .iter()
+                    .position(|x| &x.0 == category)
+                    .unwrap_or(0)
+                    + 1,
+                bbox: [rect.min.x, rect.min.y, rect.width(), rect.height()],
+                area,
+                iscrowd: 0,
+                segmentation: None,
+                keypoints: None,
+                num_keypoints: None,
+            };
+
+            match geometry {
+                Geometry::Box(_) => {}
+                Geometry::Polygon(points) => {
+                    annotation.segmentation =
+                        Some(vec![points.iter().flat_map(|x| [x.x, x.y]).collect()]);
+                }
+                Geometry::Keypoints(points) => {
+                    annotation.keypoints = Some(
+                        points
+                            .iter()
+                            .flat_map(|x| match x {
+                                Some(x) => [x.x, x.y, 2.0],
+                                None => [0.0, 0.0, 0.0],
+                            })
+                            .collect(),
+                    );
+                    annotation.num_keypoints = Some(points.iter().flatten().count());
+                }
+            }
+            coco.annotations.push(annotation);
+        }
+    }
+
+    std::fs::write(path, serde_json::to_string_pretty(&coco)? + "\n")?;
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use egui::{Pos2, Rect};
+
+/// Shape of a region, in pixels of the original image.
+#[derive(Clone)]
+pub enum Geometry {
+    Box(Rect),
+    Polygon(Vec<Pos2>),
+    /// `None` for keypoints which aren't visible
+    Keypoints(Vec<Option<Pos2>>),
+}
+
+impl Geometry {
+    /// Editable vertices with their index.
+    pub fn vertices(&self) -> Vec<(usize, Pos2)> {
+        match self {
+            Self::Box(rect) => corners(rect).into_iter().enumerate().collect(),
+            Self::Polygon(points) => points.iter().copied().enumerate().collect(),
+            Self::Keypoints(points) => points
+                .iter()
+                .enumerate()
+                .filter_map(|(i, x)| x.map(|x| (i, x)))
+                .collect(),
+        }
+    }
+
+    /// Moves a vertex. The corner of a box opposite to the moved one is
+    /// `anchor`, as the corners are swapped when the box is flipped.
+    pub fn move_vertex(&mut self, vertex: usize, pos: Pos2, anchor: Option<Pos2>) {
+        match self {
+            Self::Box(rect) => {
+                let anchor = anchor.unwrap_or_else(|| corners(rect)[(vertex + 2) % 4]);
+                *rect = Rect::from_two_pos(anchor, pos);
+            }
+            Self::Polygon(points) => {
+                if let Some(point) = points.get_mut(vertex) {
+                    *point = pos;
+                }
+            }
+            Self::Keypoints(points) => {
+                if let Some(point) = points.get_mut(vertex) {
+                    *point = Some(pos);
+                }
+            }
+        }
+    }
+
+    /// `None` for keypoints of which none is visible.
+    pub fn bounding_rect(&self) -> Option<Rect> {
+        match self {
+            Self::Box(rect) => Some(*rect),
+            _ => {
+                let points = self.vertices().into_iter().map(|x| x.1).collect::<Vec<_>>();
+                (!points.is_empty()).then(|| Rect::from_points(&points))
+            }
+        }
+    }
+
+    pub fn area(&self) -> Option<f32> {
+        match self {
+            Self::Polygon(points) => Some(polygon_area(points)),
+            _ => self.bounding_rect().map(|x| x.area()),
+        }
+    }
+}
+
+pub fn corners(rect: &Rect) -> [Pos2; 4] {
+    [
+        rect.left_top(),
+        rect.right_top(),
+        rect.right_bottom(),
+        rect.left_bottom(),
+    ]
+}
+
+/// Shoelace formula.
+fn polygon_area(points: &[Pos2]) -> f32 {
+    let sum = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum::<f32>();
+
+    sum.abs() / 2.0
+}
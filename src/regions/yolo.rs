@@ -0,0 +1,99 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use log::warn;
+
+use super::AnnotatedImage;
+use crate::config::Shape;
+
+/// Label file of an image, mirroring its directories under `input_root`.
+/// Images outside of it mirror their whole path.
+fn label_path(image: &Path, input_root: &Path) -> PathBuf {
+    let relative = image.strip_prefix(input_root).unwrap_or(image);
+    let path = relative
+        .components()
+        .filter(|x| matches!(x, Component::Normal(_)))
+        .collect::<PathBuf>();
+
+    path.with_extension("txt")
+}
+
+/// Writes a `labels/<image path>.txt` file per image and `classes.txt`. Every
+/// shape is written as its bounding box.
+pub fn write<'a>(
+    dir: &Path,
+    input_root: &Path,
+    images: impl Iterator<Item = &'a AnnotatedImage>,
+    categories: &[(String, Shape)],
+) -> Result<(), Box<dyn Error>> {
+    let labels_dir = dir.join("labels");
+    // Every label is rewritten, this removes the ones of previous layouts
+    if labels_dir.exists() {
+        std::fs::remove_dir_all(&labels_dir)?;
+    }
+    std::fs::create_dir_all(&labels_dir)?;
+    let mut written = HashMap::new();
+    let classes = categories.iter().map(|x| x.0.as_str()).collect::<Vec<_>>();
+    std::fs::write(dir.join("classes.txt"), classes.join("\n") + "\n")?;
+
+    for image in images {
+        let (width, height) = (image.width as f32, image.height as f32);
+        let mut labels = String::new();
+
+        for (category, geometry) in image.labeled_regions() {
+            let class = categories
+                .iter()
+                .position(|x| &x.0 == category)
+                .unwrap_or(0);
+            let rect = match geometry.bounding_rect() {
+                Some(rect) => rect,
+                None => {
+                    warn!(
+                        "Region of {} skipped as it has no visible point",
+                        image.path.display()
+                    );
+                    continue;
+                }
+            };
+            let center = rect.center();
+
+            writeln!(
+                labels,
+                "{} {:.6} {:.6} {:.6} {:.6}",
+                class,
+                center.x / width,
+                center.y / height,
+                rect.width() / width,
+                rect.height() / height
+            )?;
+        }
+
+        let path = label_path(&image.path, input_root);
+        if path.file_stem().is_none() {
+            warn!("{} has no file name", image.path.display());
+            continue;
+        }
+        // e.g. `img1.jpg` and `img1.png` of the same directory
+        if let Some(other) = written.insert(path.clone(), &image.path) {
+            return Err(format!(
+                "{} and {} would have the same YOLO label file {}",
+                other.display(),
+                image.path.display(),
+                path.display()
+            )
+            .into());
+        }
+
+        let path = labels_dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, labels)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use egui::{Key, TextEdit};
+
+use crate::{
+    data_loader::{read_image, Image},
+    shortcuts::shortcuts_enabled,
+};
+
+/// Browses the images of a category.
+pub struct Review {
+    category: String,
+    /// Every image of the category
+    all_paths: Vec<PathBuf>,
+    /// Images whose note matches the search
+    paths: Vec<PathBuf>,
+    index: usize,
+    image: Option<Image>,
+    /// Note and tags of the images which have some
+    notes: HashMap<PathBuf, String>,
+    search: String,
+}
+
+impl Review {
+    pub fn new(category: String, paths: Vec<PathBuf>, notes: HashMap<PathBuf, String>) -> Self {
+        let mut review = Self {
+            category,
+            all_paths: paths.clone(),
+            paths,
+            index: 0,
+            image: None,
+            notes,
+            search: String::new(),
+        };
+        review.load_current();
+
+        review
+    }
+
+    fn load_current(&mut self) {
+        self.image = self
+            .paths
+            .get(self.index)
+            .cloned()
+            .and_then(|path| read_image(path, None));
+    }
+
+    /// Keeps the images whose note contains the search, case insensitive.
+    fn filter(&mut self) {
+        let search = self.search.trim().to_lowercase();
+
+        self.paths = self
+            .all_paths
+            .iter()
+            .filter(|path| {
+                search.is_empty()
+                    || self
+                        .notes
+                        .get(*path)
+                        .is_some_and(|x| x.to_lowercase().contains(&search))
+            })
+            .cloned()
+            .collect();
+        self.index = 0;
+        self.load_current();
+    }
+
+    fn go_to(&mut self, index: usize) {
+        if index < self.paths.len() && index != self.index {
+            self.index = index;
+            self.load_current();
+        }
+    }
+
+    /// Returns `true` once the user leaves the review.
+    pub fn update(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) -> bool {
+        let mut close = false;
+
+        ui.vertical_centered(|ui| {
+            ui.heading(format!("Reviewing \"{}\"", self.category));
+
+            let keys_enabled = shortcuts_enabled(ctx);
+            ui.horizontal(|ui| {
+                if ui.button("⬅ Previous").clicked()
+                    || keys_enabled && ctx.input().key_pressed(Key::ArrowLeft)
+                {
+                    self.go_to(self.index.saturating_sub(1));
+                }
+                ui.label(format!(
+                    "{}/{}",
+                    (self.index + 1).min(self.paths.len()),
+                    self.paths.len()
+                ));
+                if ui.button("Next ➡").clicked()
+                    || keys_enabled && ctx.input().key_pressed(Key::ArrowRight)
+                {
+                    self.go_to(self.index + 1);
+                }
+                close = ui.button("Back to summary").clicked();
+
+                let search = ui
+                    .add(TextEdit::singleline(&mut self.search).hint_text("Search notes and tags"));
+                if search.changed() {
+                    self.filter();
+                }
+            });
+
+            if let Some(path) = self.paths.get(self.index) {
+                ui.label(path.display().to_string());
+                if let Some(note) = self.notes.get(path) {
+                    ui.label(egui::RichText::new(note).italics());
+                }
+            }
+        });
+
+        match &self.image {
+            Some(image) => {
+                ui.with_layout(
+                    egui::Layout::top_down_justified(egui::Align::Center),
+                    |ui| {
+                        let buffer = &image.buffer;
+                        let factors = ui.available_size() / buffer.size_vec2();
+
+                        buffer.show_scaled(ui, factors.min_elem());
+                    },
+                );
+            }
+            None if self.all_paths.is_empty() => {
+                ui.label("This category is empty");
+            }
+            None if self.paths.is_empty() => {
+                ui.label("No note matches the search");
+            }
+            None => {
+                ui.label("Couldn't read this image");
+            }
+        }
+
+        close
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use log::{info, warn};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Sample;
+
+const SAMPLE_FILE: &str = "sample.json";
+
+/// Images sampled in the first session, along with the config they were
+/// drawn with.
+#[derive(Serialize, Deserialize)]
+struct DrawnSample {
+    #[serde(flatten)]
+    sample: Sample,
+    paths: Vec<PathBuf>,
+}
+
+/// Images of the sample, in labeling order. It's drawn among `paths` once and
+/// read from `output_dir` afterwards, unless the sample config changed.
+pub fn load_sample(
+    paths: &[PathBuf],
+    sample: &Sample,
+    output_dir: &Path,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let sample_path = output_dir.join(SAMPLE_FILE);
+    if sample_path.exists() {
+        let drawn: DrawnSample = serde_json::from_str(&std::fs::read_to_string(&sample_path)?)?;
+
+        if &drawn.sample == sample {
+            info!("Readed a sample of {} images", drawn.paths.len());
+            return Ok(drawn.paths);
+        }
+        warn!("The sample config changed, drawing a new sample");
+    }
+
+    let drawn = DrawnSample {
+        sample: sample.clone(),
+        paths: sample_paths(paths.to_vec(), sample),
+    };
+    std::fs::write(sample_path, serde_json::to_string_pretty(&drawn)? + "\n")?;
+
+    Ok(drawn.paths)
+}
+
+/// Keeps a random subset of `paths`, the same one for a given seed and input.
+fn sample_paths(mut paths: Vec<PathBuf>, sample: &Sample) -> Vec<PathBuf> {
+    let seed = sample.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let mut rng = StdRng::seed_from_u64(seed);
+    let total = paths.len();
+
+    // Directories aren't listed in a stable order
+    paths.sort();
+
+    let groups = if sample.stratified {
+        let mut groups: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+
+        paths.into_iter().for_each(|path| {
+            let parent = path.parent().map(PathBuf::from).unwrap_or_default();
+            groups.entry(parent).or_default().push(path);
+        });
+        groups.into_values().collect()
+    } else {
+        vec![paths]
+    };
+
+    let mut sampled = vec![];
+    for mut group in groups {
+        let size = sample_size(sample, group.len(), total);
+
+        group.shuffle(&mut rng);
+        group.truncate(size);
+        sampled.extend(group);
+    }
+    sampled.shuffle(&mut rng);
+
+    info!(
+        "Sampled {} of {} images with seed {}",
+        sampled.len(),
+        total,
+        seed
+    );
+
+    sampled
+}
+
+/// Size of the sample taken from a group of `len` images out of `total`.
+fn sample_size(sample: &Sample, len: usize, total: usize) -> usize {
+    let size = match (sample.size, sample.fraction) {
+        (Some(size), _) => size as f64 * len as f64 / total.max(1) as f64,
+        (None, Some(fraction)) => fraction * len as f64,
+        (None, None) => len as f64,
+    };
+
+    // Every group keeps at least one image
+    (size.round() as usize).clamp(len.min(1), len)
+}
@@ -0,0 +1,4 @@
+/// Whether key shortcuts apply. Typing in a text field must not trigger them.
+pub fn shortcuts_enabled(ctx: &egui::Context) -> bool {
+    !ctx.wants_keyboard_input()
+}
@@ -0,0 +1,148 @@
+use std::time::{Duration, Instant};
+
+use egui::{
+    plot::{Bar, BarChart, Plot},
+    CollapsingHeader, Grid, ProgressBar,
+};
+
+use crate::{category::CategoriesHolder, progression::Progression};
+
+pub struct Statistics {
+    start: Instant,
+    nb_labeled: usize,
+}
+
+impl Statistics {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            nb_labeled: 0,
+        }
+    }
+
+    pub fn record_label(&mut self) {
+        self.nb_labeled += 1;
+    }
+
+    pub fn get_nb_labeled(&self) -> usize {
+        self.nb_labeled
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Images labeled per minute during this session.
+    pub fn rate(&self) -> f32 {
+        let minutes = self.elapsed().as_secs_f32() / 60.0;
+
+        if minutes > 0.0 {
+            self.nb_labeled as f32 / minutes
+        } else {
+            0.0
+        }
+    }
+
+    pub fn eta(&self, progression: &Progression) -> Option<Duration> {
+        let rate = self.rate();
+
+        if rate > 0.0 {
+            let minutes = progression.get_nb_remaining_step() as f32 / rate;
+            Some(Duration::from_secs_f32(minutes * 60.0))
+        } else {
+            None
+        }
+    }
+
+    pub fn update(
+        &self,
+        ui: &mut egui::Ui,
+        progression: &Progression,
+        category: &CategoriesHolder,
+    ) {
+        CollapsingHeader::new("Statistics")
+            .default_open(true)
+            .show(ui, |ui| {
+                let counts = category.counts();
+
+                Grid::new("Category counts").striped(true).show(ui, |ui| {
+                    ui.label("Category");
+                    ui.label("Own");
+                    ui.label("Total");
+                    ui.end_row();
+
+                    counts.iter().for_each(|count| {
+                        ui.label(format!("{}{}", "  ".repeat(count.depth), count.name));
+                        ui.label(count.own.to_string());
+                        ui.label(count.total.to_string());
+                        ui.end_row();
+                    });
+                });
+
+                ui.separator();
+                ui.label(format!(
+                    "Session: {} labeled in {}",
+                    self.nb_labeled,
+                    format_duration(self.elapsed())
+                ));
+                ui.label(format!("Rate: {:.1} images/min", self.rate()));
+                ui.label(format!(
+                    "ETA: {}",
+                    self.eta(progression)
+                        .map_or_else(|| "unknown".to_string(), format_duration)
+                ));
+
+                if !progression.get_directories().is_empty() {
+                    CollapsingHeader::new("Directories").show(ui, |ui| {
+                        progression.get_directories().iter().for_each(|directory| {
+                            let name = directory
+                                .path
+                                .file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy();
+
+                            ui.add(
+                                ProgressBar::new(
+                                    directory.done as f32 / directory.total.max(1) as f32,
+                                )
+                                .text(format!("{}: {}/{}", name, directory.done, directory.total)),
+                            );
+                        });
+                    });
+                }
+
+                let names = counts.iter().map(|x| x.name.clone()).collect::<Vec<_>>();
+                let bars = counts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, count)| Bar::new(i as f64, count.total as f64).name(&count.name))
+                    .collect();
+
+                Plot::new("Class distribution")
+                    .height(150.0)
+                    .allow_drag(false)
+                    .allow_zoom(false)
+                    .allow_scroll(false)
+                    .x_axis_formatter(move |x, _| {
+                        let i = x.round();
+
+                        match names.get(i as usize) {
+                            Some(name) if i >= 0.0 && (x - i).abs() < f64::EPSILON => name.clone(),
+                            _ => String::new(),
+                        }
+                    })
+                    .show(ui, |plot_ui| plot_ui.bar_chart(BarChart::new(bars)));
+            });
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    format!(
+        "{}h {:02}m {:02}s",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
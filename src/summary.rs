@@ -0,0 +1,97 @@
+use egui::{ComboBox, Grid};
+
+use crate::{
+    category::CategoriesHolder,
+    statistics::{format_duration, Statistics},
+};
+
+pub enum SummaryAction {
+    Export,
+    Review(String),
+    Quit,
+}
+
+/// Completion screen shown once every image has been categorized or every
+/// quota is met.
+pub struct Summary {
+    title: String,
+    selected: Option<String>,
+    status: Option<String>,
+}
+
+impl Summary {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            selected: None,
+            status: None,
+        }
+    }
+
+    pub fn set_status(&mut self, status: String) {
+        self.status = Some(status);
+    }
+
+    pub fn update(
+        &mut self,
+        ui: &mut egui::Ui,
+        category: &CategoriesHolder,
+        statistics: &Statistics,
+        nb_unreadable: usize,
+    ) -> Option<SummaryAction> {
+        let mut action = None;
+
+        ui.vertical_centered(|ui| {
+            ui.heading(&self.title);
+        });
+        ui.separator();
+
+        Grid::new("Summary totals").striped(true).show(ui, |ui| {
+            ui.label("Category");
+            ui.label("Images");
+            ui.end_row();
+
+            category.counts().iter().for_each(|count| {
+                ui.label(format!("{}{}", "  ".repeat(count.depth), count.name));
+                ui.label(count.total.to_string());
+                ui.end_row();
+            });
+        });
+
+        ui.separator();
+        ui.label(format!(
+            "{} images labeled in {}",
+            statistics.get_nb_labeled(),
+            format_duration(statistics.elapsed())
+        ));
+        ui.label(format!("{} unreadable images", nb_unreadable));
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui.button("Export").clicked() {
+                action = Some(SummaryAction::Export);
+            }
+
+            ComboBox::from_id_source("Reviewed category")
+                .selected_text(self.selected.as_deref().unwrap_or("Select a category"))
+                .show_ui(ui, |ui| {
+                    category.names().into_iter().for_each(|name| {
+                        ui.selectable_value(&mut self.selected, Some(name.clone()), name);
+                    });
+                });
+            if ui.button("Review").clicked() {
+                action = self.selected.clone().map(SummaryAction::Review);
+            }
+
+            if ui.button("Quit").clicked() {
+                action = Some(SummaryAction::Quit);
+            }
+        });
+
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+
+        action
+    }
+}
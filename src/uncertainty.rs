@@ -0,0 +1,145 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    error::Error,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use csv::{ReaderBuilder, StringRecord};
+use log::{info, warn};
+
+use crate::config::{Queue, Strategy};
+
+/// Orders images so that those the model is the least sure about are labeled
+/// first.
+pub struct UncertaintyQueue {
+    config: Queue,
+    uncertainties: HashMap<PathBuf, f32>,
+    modified: Option<SystemTime>,
+    nb_labeled: usize,
+}
+
+impl UncertaintyQueue {
+    pub fn load(config: Queue) -> Result<Self, Box<dyn Error>> {
+        let modified = modification_time(&config.scores);
+        let uncertainties = read_uncertainties(&config.scores, config.strategy)?;
+
+        Ok(Self {
+            config,
+            uncertainties,
+            modified,
+            nb_labeled: 0,
+        })
+    }
+
+    pub fn config(&self) -> &Queue {
+        &self.config
+    }
+
+    /// Sorts `paths` by increasing uncertainty, images without scores first,
+    /// as the last images are read first.
+    pub fn sort(&self, paths: &mut [PathBuf]) {
+        paths.sort_by(|a, b| {
+            let a = self
+                .uncertainties
+                .get(a)
+                .copied()
+                .unwrap_or(f32::NEG_INFINITY);
+            let b = self
+                .uncertainties
+                .get(b)
+                .copied()
+                .unwrap_or(f32::NEG_INFINITY);
+
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        });
+    }
+
+    /// Reloads the scores once a batch is labeled and the file changed.
+    /// Returns `true` when the images need to be sorted again.
+    pub fn record_label(&mut self) -> bool {
+        self.nb_labeled += 1;
+        if self.nb_labeled < self.config.batch_size {
+            return false;
+        }
+        self.nb_labeled = 0;
+
+        let modified = modification_time(&self.config.scores);
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+
+        match read_uncertainties(&self.config.scores, self.config.strategy) {
+            Ok(uncertainties) => {
+                info!("Reloaded scores from {}", self.config.scores.display());
+                self.uncertainties = uncertainties;
+
+                true
+            }
+            Err(e) => {
+                warn!("Couldn't reload {}: {}", self.config.scores.display(), e);
+
+                false
+            }
+        }
+    }
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|x| x.modified()).ok()
+}
+
+fn read_uncertainties(
+    path: &Path,
+    strategy: Strategy,
+) -> Result<HashMap<PathBuf, f32>, Box<dyn Error>> {
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?;
+    let mut uncertainties = HashMap::new();
+
+    for record in rdr.records() {
+        let record: StringRecord = record?;
+        let probabilities = record
+            .iter()
+            .skip(1)
+            .map(|x| x.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(path) = record.get(0) {
+            uncertainties.insert(PathBuf::from(path), uncertainty(probabilities, strategy));
+        }
+    }
+    info!(
+        "Readed {} scores from {}",
+        uncertainties.len(),
+        path.display()
+    );
+
+    Ok(uncertainties)
+}
+
+/// The higher, the less sure the model is.
+fn uncertainty(mut probabilities: Vec<f32>, strategy: Strategy) -> f32 {
+    // A single score is the probability of the positive class
+    if let [p] = probabilities[..] {
+        probabilities.push(1.0 - p);
+    }
+    probabilities.sort_by(|a, b| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+
+    match strategy {
+        Strategy::LeastConfidence => 1.0 - probabilities.first().copied().unwrap_or(0.0),
+        Strategy::Entropy => -probabilities
+            .iter()
+            .filter(|p| **p > 0.0)
+            .map(|p| p * p.ln())
+            .sum::<f32>(),
+        Strategy::Margin => match probabilities[..] {
+            [first, second, ..] => 1.0 - (first - second),
+            _ => 0.0,
+        },
+    }
+}